@@ -0,0 +1,50 @@
+use base64::{engine::general_purpose, Engine as _};
+
+use crate::storage::Storage;
+
+/// Storage collection holding one document per distinct offloaded payload,
+/// keyed by its BLAKE3 hex digest: `{ id: <hex-hash>, data: <base64> }`.
+/// Separate from `storage::blob`'s chunked at-rest blob store: that one
+/// exists to keep a single backend document's own bytes small on disk; this
+/// one holds whole, un-chunked `CRDTOperation` payloads that were too big
+/// to travel inline on the wire, fetched peer-to-peer on demand via
+/// `MessageType::BlockRequest`/`BlockReply` rather than assembled purely
+/// from local storage.
+const BLOCK_STORE_COLLECTION: &str = "_operation_blocks";
+
+/// Payload size, in bytes, above which `DistributedCollection` offloads a
+/// `CRDTOperation`'s document payload into the block store and replaces it
+/// inline with a `payload_ref` hash. Used whenever
+/// `NetworkConfig::inline_threshold` is zero.
+pub const DEFAULT_INLINE_THRESHOLD: usize = 3 * 1024;
+
+/// store_block writes `bytes` under its BLAKE3 hex digest, if not already
+/// present, and returns that digest as the `payload_ref` to carry on the
+/// wire instead of the inline bytes. Storing under content hash means
+/// identical payloads across documents or repeated updates are deduped for
+/// free.
+pub(crate) async fn store_block(storage: &dyn Storage, bytes: &[u8]) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+    let hash = blake3::hash(bytes).to_hex().to_string();
+
+    if storage.find(BLOCK_STORE_COLLECTION, &hash).await?.is_none() {
+        let mut doc = std::collections::HashMap::new();
+        doc.insert("id".to_string(), serde_json::Value::String(hash.clone()));
+        doc.insert("data".to_string(), serde_json::Value::String(general_purpose::STANDARD.encode(bytes)));
+        storage.insert(BLOCK_STORE_COLLECTION, doc).await?;
+    }
+
+    Ok(hash)
+}
+
+/// load_block reads back the bytes previously stored under `hash`, if this
+/// node has them locally (`None` if not — the caller should then fetch it
+/// from a peer with `MessageType::BlockRequest`).
+pub(crate) async fn load_block(storage: &dyn Storage, hash: &str) -> Result<Option<Vec<u8>>, Box<dyn std::error::Error + Send + Sync>> {
+    let doc = match storage.find(BLOCK_STORE_COLLECTION, hash).await? {
+        Some(doc) => doc,
+        None => return Ok(None),
+    };
+
+    let data = doc.get("data").and_then(|v| v.as_str()).ok_or("block entry missing data")?;
+    Ok(Some(general_purpose::STANDARD.decode(data)?))
+}
@@ -0,0 +1,173 @@
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+/// Codec converts a Rust value to and from the bytes carried in
+/// `ProtocolMessage.payload`. Each wire format (`JsonCodec`,
+/// `BincodeCodec`, `PostcardCodec`, `MessagePackCodec`) implements this the
+/// same way `Storage` has one trait and several backends.
+pub trait Codec {
+    fn encode<T: Serialize>(&self, value: &T) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>>;
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, Box<dyn std::error::Error + Send + Sync>>;
+}
+
+/// JsonCodec is the universal fallback: verbose and slower on the wire than
+/// the binary codecs below, but every peer is guaranteed to support it, and
+/// it stays human-readable for debugging. Always available, regardless of
+/// cargo features.
+pub struct JsonCodec;
+
+impl Codec for JsonCodec {
+    fn encode<T: Serialize>(&self, value: &T) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(serde_json::to_vec(value)?)
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(serde_json::from_slice(bytes)?)
+    }
+}
+
+/// BincodeCodec is a compact binary encoding, available behind the
+/// `codec-bincode` feature.
+#[cfg(feature = "codec-bincode")]
+pub struct BincodeCodec;
+
+#[cfg(feature = "codec-bincode")]
+impl Codec for BincodeCodec {
+    fn encode<T: Serialize>(&self, value: &T) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(bincode::serialize(value)?)
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(bincode::deserialize(bytes)?)
+    }
+}
+
+/// PostcardCodec is a `no_std`-friendly, varint-packed encoding aimed at the
+/// most bandwidth- and memory-constrained peers (e.g. embedded mesh nodes),
+/// available behind the `codec-postcard` feature.
+#[cfg(feature = "codec-postcard")]
+pub struct PostcardCodec;
+
+#[cfg(feature = "codec-postcard")]
+impl Codec for PostcardCodec {
+    fn encode<T: Serialize>(&self, value: &T) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(postcard::to_allocvec(value)?)
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(postcard::from_bytes(bytes)?)
+    }
+}
+
+/// MessagePackCodec is a compact, self-describing binary encoding, available
+/// behind the `codec-msgpack` feature. This is distinct from the
+/// `rmp_serde` framing `wire::encode_message` already applies to the whole
+/// `ProtocolMessage` envelope on every connection: this codec only governs
+/// how the inner `payload` bytes are produced, so the two compose (a
+/// MessagePack payload nested inside a MessagePack-framed message is no
+/// different on the wire than a JSON payload nested the same way).
+#[cfg(feature = "codec-msgpack")]
+pub struct MessagePackCodec;
+
+#[cfg(feature = "codec-msgpack")]
+impl Codec for MessagePackCodec {
+    fn encode<T: Serialize>(&self, value: &T) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(rmp_serde::to_vec(value)?)
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(rmp_serde::from_slice(bytes)?)
+    }
+}
+
+/// CodecKind identifies which `Codec` a `ProtocolMessage.payload` was
+/// encoded with, traveling alongside the bytes so every message
+/// self-describes its own encoding and a receiver never has to guess or
+/// negotiate before it can decode a message that's already arrived.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CodecKind {
+    Json,
+    Bincode,
+    Postcard,
+    MessagePack,
+}
+
+impl CodecKind {
+    /// all lists every codec this build was compiled to support, most
+    /// compact first, with `Json` last as the universal fallback. This is
+    /// "our" side of `negotiate`.
+    pub fn all() -> Vec<CodecKind> {
+        vec![
+            #[cfg(feature = "codec-postcard")]
+            CodecKind::Postcard,
+            #[cfg(feature = "codec-bincode")]
+            CodecKind::Bincode,
+            #[cfg(feature = "codec-msgpack")]
+            CodecKind::MessagePack,
+            CodecKind::Json,
+        ]
+    }
+
+    /// negotiate picks the most compact codec present in both `ours` (in
+    /// preference order) and `theirs`, falling back to `Json` if the two
+    /// sides share nothing else — every build supports `Json`, so this
+    /// always succeeds.
+    pub fn negotiate(ours: &[CodecKind], theirs: &[CodecKind]) -> CodecKind {
+        ours.iter().find(|c| theirs.contains(c)).copied().unwrap_or(CodecKind::Json)
+    }
+
+    /// encode serializes `value` with the `Codec` this kind identifies.
+    pub fn encode<T: Serialize>(self, value: &T) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+        match self {
+            CodecKind::Json => JsonCodec.encode(value),
+            #[cfg(feature = "codec-bincode")]
+            CodecKind::Bincode => BincodeCodec.encode(value),
+            #[cfg(not(feature = "codec-bincode"))]
+            CodecKind::Bincode => Err("codec-bincode feature not enabled".into()),
+            #[cfg(feature = "codec-postcard")]
+            CodecKind::Postcard => PostcardCodec.encode(value),
+            #[cfg(not(feature = "codec-postcard"))]
+            CodecKind::Postcard => Err("codec-postcard feature not enabled".into()),
+            #[cfg(feature = "codec-msgpack")]
+            CodecKind::MessagePack => MessagePackCodec.encode(value),
+            #[cfg(not(feature = "codec-msgpack"))]
+            CodecKind::MessagePack => Err("codec-msgpack feature not enabled".into()),
+        }
+    }
+
+    /// decode deserializes `bytes` that were encoded with the `Codec` this
+    /// kind identifies.
+    pub fn decode<T: DeserializeOwned>(self, bytes: &[u8]) -> Result<T, Box<dyn std::error::Error + Send + Sync>> {
+        match self {
+            CodecKind::Json => JsonCodec.decode(bytes),
+            #[cfg(feature = "codec-bincode")]
+            CodecKind::Bincode => BincodeCodec.decode(bytes),
+            #[cfg(not(feature = "codec-bincode"))]
+            CodecKind::Bincode => Err("codec-bincode feature not enabled".into()),
+            #[cfg(feature = "codec-postcard")]
+            CodecKind::Postcard => PostcardCodec.decode(bytes),
+            #[cfg(not(feature = "codec-postcard"))]
+            CodecKind::Postcard => Err("codec-postcard feature not enabled".into()),
+            #[cfg(feature = "codec-msgpack")]
+            CodecKind::MessagePack => MessagePackCodec.decode(bytes),
+            #[cfg(not(feature = "codec-msgpack"))]
+            CodecKind::MessagePack => Err("codec-msgpack feature not enabled".into()),
+        }
+    }
+}
+
+/// json_payload encodes `value` as a `ProtocolMessage`'s `(payload,
+/// content_codec)` pair using `CodecKind::Json`, the fallback every peer
+/// can decode. JSON-encoding a `serde_json::Value` can't practically fail,
+/// so this takes the value directly rather than forcing every call site
+/// that only ever sends JSON to thread a `Result`.
+pub fn json_payload(value: serde_json::Value) -> (Vec<u8>, CodecKind) {
+    (serde_json::to_vec(&value).unwrap_or_default(), CodecKind::Json)
+}
+
+/// payload_value decodes `bytes` (encoded with `codec`) back into a
+/// `serde_json::Value`, the shape handler code already expects to
+/// destructure with `.get(...)`.
+pub fn payload_value(codec: CodecKind, bytes: &[u8]) -> serde_json::Value {
+    codec.decode(bytes).unwrap_or(serde_json::Value::Null)
+}
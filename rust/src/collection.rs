@@ -1,10 +1,80 @@
 use std::collections::HashMap;
 use std::sync::Arc;
+use base64::{engine::general_purpose, Engine as _};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use tokio::sync::RwLock;
+use crate::clock::VectorClock;
 use crate::types::*;
 use crate::storage::Storage;
 use crate::network::Network;
 use crate::resolver::CRDTResolver;
+use crate::time::{SystemTimeSource, TimeSource};
+use crate::crypto::signable::Signable;
+use crate::blockstore;
+
+/// How many operations accumulate in a collection's log before
+/// `append_operation` writes a fresh checkpoint and compacts the log. See
+/// `DistributedCollection::checkpoint`.
+const CHECKPOINT_INTERVAL: usize = 64;
+
+/// oplog_collection_name is the storage collection backing `name`'s
+/// append-only `CRDTOperation` log. `pub(crate)` so `replication`'s
+/// operation-level anti-entropy (see `ReplicationEngine`) can read and
+/// append to the same log this collection writes through
+/// `append_operation`.
+pub(crate) fn oplog_collection_name(name: &str) -> String {
+    format!("_oplog_{}", name)
+}
+
+/// checkpoint_collection_name is the storage collection backing `name`'s
+/// checkpoints (see `Checkpoint`).
+fn checkpoint_collection_name(name: &str) -> String {
+    format!("_checkpoint_{}", name)
+}
+
+/// Checkpoint is an immutable, checksummed snapshot of a collection's
+/// materialized state as of `vector`, written every `CHECKPOINT_INTERVAL`
+/// operations so `force_sync` can recover by replaying only the operations
+/// since the snapshot instead of the whole log. `seq` orders checkpoints
+/// (its zero-padded form doubles as `id`, so storage's id-keyed find/delete
+/// can address it); `checksum` lets `is_valid` detect one that was only
+/// partially written.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Checkpoint {
+    id: String,
+    seq: u64,
+    vector: VectorClock,
+    documents: Vec<DistributedDocument>,
+    checksum: String,
+}
+
+impl Checkpoint {
+    fn new(seq: u64, vector: VectorClock, documents: Vec<DistributedDocument>) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let checksum = checksum_for(&documents)?;
+        Ok(Checkpoint {
+            id: format!("{:020}", seq),
+            seq,
+            vector,
+            documents,
+            checksum,
+        })
+    }
+
+    /// is_valid recomputes the checksum over `documents` and compares it
+    /// against the one recorded at write time, catching a checkpoint that
+    /// was only partially written (e.g. the process died mid-write).
+    fn is_valid(&self) -> bool {
+        matches!(checksum_for(&self.documents), Ok(checksum) if checksum == self.checksum)
+    }
+}
+
+fn checksum_for(documents: &[DistributedDocument]) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+    let bytes = serde_json::to_vec(documents)?;
+    let mut hasher = Sha256::new();
+    hasher.update(&bytes);
+    Ok(format!("{:x}", hasher.finalize()))
+}
 
 /// Collection represents a distributed collection of documents
 pub struct DistributedCollection {
@@ -14,15 +84,20 @@ pub struct DistributedCollection {
     network_id: Option<String>,
     resolver: CRDTResolver,
     sync_state: Arc<RwLock<SyncState>>,
+    time_source: Arc<dyn TimeSource>,
 }
 
 impl DistributedCollection {
-    /// NewDistributedCollection creates a new distributed collection
+    /// NewDistributedCollection creates a new distributed collection,
+    /// stamping CRDT timestamps from the real wall clock
+    /// (`SystemTimeSource`). Use `with_time_source` to swap in a
+    /// `MockTimeSource` for deterministic last-writer-wins/merge tests.
     pub fn new(
         name: String,
         storage: Arc<dyn Storage>,
         network: Arc<dyn Network>,
     ) -> Self {
+        let time_source: Arc<dyn TimeSource> = Arc::new(SystemTimeSource);
         DistributedCollection {
             name: name.clone(),
             storage,
@@ -32,24 +107,318 @@ impl DistributedCollection {
             sync_state: Arc::new(RwLock::new(SyncState {
                 collection: name.clone(),
                 network_id: "".to_string(),
-                local_vector: crate::clock::VectorClock::new(),
-                last_sync: chrono::Utc::now(),
-                pending_operations: vec![],
+                local_vector: VectorClock::new(),
+                last_sync: time_source.now(),
                 staged_entries: vec![],
+                pending_proposals: HashMap::new(),
                 sync_in_progress: false,
             })),
+            time_source,
+        }
+    }
+
+    /// with_time_source swaps in the given `TimeSource`, used to stamp CRDT
+    /// operation/document timestamps instead of the wall clock.
+    pub fn with_time_source(mut self, time_source: Arc<dyn TimeSource>) -> Self {
+        self.time_source = time_source;
+        self
+    }
+
+    /// next_vector increments and returns this collection's local vector
+    /// clock. Every operation must be stamped from this shared counter
+    /// (rather than a fresh one-off clock) so checkpoint/replay comparisons
+    /// in `force_sync` see a meaningful, monotonically advancing history.
+    async fn next_vector(&self) -> VectorClock {
+        let mut sync_state = self.sync_state.write().await;
+        sync_state.local_vector = sync_state.local_vector.clone().increment(self.network.get_peer_id());
+        sync_state.local_vector.clone()
+    }
+
+    /// inline_threshold looks up this collection's attached network's
+    /// `NetworkConfig::inline_threshold`, falling back to the package
+    /// default (`blockstore::DEFAULT_INLINE_THRESHOLD`) if unset or the
+    /// collection isn't attached to a network yet.
+    fn inline_threshold(&self) -> usize {
+        let configured = self.network_id.as_ref()
+            .and_then(|network_id| self.network.get_networks().into_iter().find(|cfg| &cfg.network_id == network_id))
+            .map(|cfg| cfg.inline_threshold)
+            .unwrap_or(0);
+        if configured == 0 { blockstore::DEFAULT_INLINE_THRESHOLD } else { configured }
+    }
+
+    /// offload_if_large hashes and stashes `operation.data`'s `payload` in
+    /// the content-addressed block store, replacing it with a
+    /// `payload_ref`, if its serialized size exceeds `inline_threshold`.
+    /// Leaves small payloads inline as before. Must run before `sign`/
+    /// `append_operation`/`broadcast_operation` so the oplog and the wire
+    /// both carry the offloaded form.
+    async fn offload_if_large(&self, operation: &mut CRDTOperation) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let data = match operation.data.as_mut() {
+            Some(data) => data,
+            None => return Ok(()),
+        };
+        let payload = match data.payload.as_ref() {
+            Some(payload) => payload,
+            None => return Ok(()),
+        };
+
+        let bytes = serde_json::to_vec(payload)?;
+        if bytes.len() <= self.inline_threshold() {
+            return Ok(());
+        }
+
+        operation.payload_ref = Some(blockstore::store_block(self.storage.as_ref(), &bytes).await?);
+        data.payload = None;
+        Ok(())
+    }
+
+    /// resolve_payload_ref fills in `doc`'s payload from the local block
+    /// store when `payload_ref` names one, leaving `doc` unchanged if it's
+    /// `None`. Used by `force_sync`'s purely local replay, so unlike
+    /// `replication::ReplicationEngine::resolve_payload_ref` it never
+    /// reaches out to a peer: every block `offload_if_large` or a prior
+    /// `ReplicationEngine::fetch_block` has ever produced locally is kept
+    /// in this node's own block store, so a miss here means the block
+    /// simply hasn't reached this node by any path yet.
+    async fn resolve_payload_ref(&self, mut doc: DistributedDocument, payload_ref: &Option<String>) -> Option<DistributedDocument> {
+        let hash = match payload_ref {
+            Some(hash) => hash,
+            None => return Some(doc),
+        };
+        let bytes = match blockstore::load_block(self.storage.as_ref(), hash).await {
+            Ok(Some(bytes)) => bytes,
+            _ => return None,
+        };
+        doc.payload = serde_json::from_slice(&bytes).ok();
+        Some(doc)
+    }
+
+    /// post_quorum_key_id looks up this collection's attached network's
+    /// `NetworkConfig::post_quorum_key_id`, or `None` if it isn't attached or
+    /// the network has no post-quorum key configured (see
+    /// `crate::database::DistributedDatabase::bootstrap_post_quorum_key`).
+    fn post_quorum_key_id(&self) -> Option<String> {
+        let network_id = self.network_id.as_ref()?;
+        let key_id = self.network.get_networks().into_iter()
+            .find(|cfg| &cfg.network_id == network_id)?
+            .post_quorum_key_id;
+        if key_id.is_empty() { None } else { Some(key_id) }
+    }
+
+    /// stage_for_posting marks document `id` with `stage = "post-pending"`
+    /// and records it in `SyncState::staged_entries`, ready for
+    /// `propose_post` to carry it through the threshold-signature quorum
+    /// that promotes it to a KNIRVGRAPH transaction. A no-op if the
+    /// document doesn't exist.
+    pub async fn stage_for_posting(&self, id: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let existing = match self.storage.find(&self.name, id).await? {
+            Some(doc) => doc,
+            None => return Ok(()),
+        };
+        let mut doc: DistributedDocument = serde_json::from_value(serde_json::to_value(existing)?)?;
+        doc.stage = Some("post-pending".to_string());
+        let storage_doc = serde_json::from_value(serde_json::to_value(&doc)?)?;
+        self.storage.insert(&self.name, storage_doc).await?;
+
+        let mut sync_state = self.sync_state.write().await;
+        if !sync_state.staged_entries.iter().any(|existing| existing == id) {
+            sync_state.staged_entries.push(id.to_string());
+        }
+        Ok(())
+    }
+
+    /// propose_post starts a threshold-signature quorum round for staged
+    /// document `id`: broadcasts a `PostProposal` carrying the document's
+    /// canonical bytes (see `post_quorum::canonical_document_bytes`) to
+    /// every peer on the attached network, and records a `PostQuorum` in
+    /// `SyncState::pending_proposals` to collect their `PostSig` replies
+    /// against. Fails if the collection isn't attached to a network with a
+    /// `post_quorum_key_id` configured, or `id` isn't currently staged.
+    pub async fn propose_post(&self, id: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let network_id = self.network_id.clone().ok_or("collection is not attached to a network")?;
+        let key_id = self.post_quorum_key_id().ok_or("network has no post_quorum_key_id configured")?;
+
+        let existing = self.storage.find(&self.name, id).await?.ok_or("no such document")?;
+        let doc: DistributedDocument = serde_json::from_value(serde_json::to_value(existing)?)?;
+        if doc.stage.as_deref() != Some("post-pending") {
+            return Err(format!("document {} is not staged for posting", id).into());
+        }
+
+        let threshold = crate::post_quorum::key_threshold(self.storage.as_ref(), &key_id).await?
+            .ok_or_else(|| format!("no key-share registry entry for post-quorum key {}", key_id))?;
+
+        {
+            let mut sync_state = self.sync_state.write().await;
+            sync_state.pending_proposals.insert(id.to_string(), PostQuorum {
+                collection: self.name.clone(),
+                document_id: id.to_string(),
+                key_id: key_id.clone(),
+                vector: doc.vector.clone(),
+                threshold,
+                shares: HashMap::new(),
+            });
+        }
+
+        let bytes = crate::post_quorum::canonical_document_bytes(&doc);
+        let (payload, content_codec) = crate::codec::json_payload(serde_json::json!({
+            "collection": self.name,
+            "document_id": id,
+            "key_id": key_id,
+            "bytes": general_purpose::STANDARD.encode(&bytes),
+        }));
+        let msg = ProtocolMessage {
+            msg_type: MessageType::PostProposal,
+            network_id: network_id.clone(),
+            sender_id: self.network.get_peer_id(),
+            timestamp: self.time_source.now().timestamp(),
+            payload,
+            content_codec,
+            request_id: None,
+            signature: None,
+        };
+        self.network.broadcast_message(&network_id, msg).await
+    }
+
+    /// record_post_sig folds one peer's `PostSig` contribution into the
+    /// in-flight `PostQuorum` for `document_id`, keyed by `peer_id` so a
+    /// repeat reply from the same peer overwrites rather than double-counts.
+    /// Once `threshold` distinct peers have contributed, attempts to
+    /// reconstruct the group signing key and MAC the document with it — see
+    /// `post_quorum::try_finalize`. A no-op if there's no matching
+    /// in-flight proposal (already finalized, GC'd, or never started).
+    pub(crate) async fn record_post_sig(&self, document_id: &str, peer_id: &str, share: PostShare) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let quorum = {
+            let mut sync_state = self.sync_state.write().await;
+            let quorum = match sync_state.pending_proposals.get_mut(document_id) {
+                Some(quorum) => quorum,
+                None => return Ok(()),
+            };
+            quorum.shares.insert(peer_id.to_string(), share);
+            if quorum.shares.len() < quorum.threshold as usize {
+                return Ok(());
+            }
+            quorum.clone()
+        };
+
+        if crate::post_quorum::try_finalize(self.storage.as_ref(), &quorum).await? {
+            let mut sync_state = self.sync_state.write().await;
+            sync_state.pending_proposals.remove(document_id);
+            sync_state.staged_entries.retain(|entry| entry != document_id);
         }
+        Ok(())
+    }
+
+    /// gc_pending_proposal abandons document `id`'s in-flight post-quorum
+    /// proposal, if any: called whenever the document is deleted or its
+    /// vector clock advances again before quorum was reached, since the
+    /// canonical bytes a completed quorum would sign are no longer current.
+    async fn gc_pending_proposal(&self, id: &str) {
+        self.sync_state.write().await.pending_proposals.remove(id);
+    }
+
+    /// append_operation persists `operation` to this collection's
+    /// append-only operation log and, once the log has grown past
+    /// `CHECKPOINT_INTERVAL` entries, writes a fresh checkpoint and compacts
+    /// it (see `checkpoint`).
+    async fn append_operation(&self, operation: &CRDTOperation) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let oplog_name = oplog_collection_name(&self.name);
+        let log_doc = serde_json::from_value(serde_json::to_value(operation)?)?;
+        self.storage.insert(&oplog_name, log_doc).await?;
+
+        if self.storage.find_all(&oplog_name).await?.len() >= CHECKPOINT_INTERVAL {
+            self.checkpoint().await?;
+        }
+
+        Ok(())
+    }
+
+    /// broadcast_operation sends `operation` to the collection's attached
+    /// network, if any. A no-op when the collection isn't attached. This is
+    /// a best-effort, fire-and-forget fast path only: a peer that's briefly
+    /// offline simply misses the broadcast, and is instead caught up later
+    /// by `replication::ReplicationEngine`'s background operation-log
+    /// anti-entropy, which gossips against the durable oplog this operation
+    /// was already written to by `append_operation`.
+    async fn broadcast_operation(&self, operation: &CRDTOperation) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let network_id = match &self.network_id {
+            Some(network_id) => network_id,
+            None => return Ok(()),
+        };
+
+        let (payload, content_codec) = crate::codec::json_payload(serde_json::to_value(operation)?);
+        let msg = ProtocolMessage {
+            msg_type: MessageType::Operation,
+            network_id: network_id.clone(),
+            sender_id: self.network.get_peer_id(),
+            timestamp: self.time_source.now().timestamp(),
+            payload,
+            content_codec,
+            request_id: None,
+            signature: None,
+        };
+        self.network.broadcast_message(network_id, msg).await
+    }
+
+    /// checkpoint materializes the collection's current on-disk state into
+    /// a new, checksummed `Checkpoint` tagged with the current vector
+    /// clock, then compacts the operation log. Exactly the two newest
+    /// checkpoints are retained: the fresh one, and the previous one as a
+    /// fallback in case the fresh write turns out to have been partial (see
+    /// `Checkpoint::is_valid` and `force_sync`). The log is only compacted
+    /// down to that fallback's vector clock — never past it — so replay
+    /// from it is always possible.
+    async fn checkpoint(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let checkpoint_collection = checkpoint_collection_name(&self.name);
+
+        let mut existing: Vec<Checkpoint> = self.storage.find_all(&checkpoint_collection).await?
+            .into_iter()
+            .filter_map(|doc| serde_json::from_value(serde_json::to_value(doc).ok()?).ok())
+            .collect();
+        existing.sort_by_key(|checkpoint| checkpoint.seq);
+
+        let documents: Vec<DistributedDocument> = self.storage.find_all(&self.name).await?
+            .into_iter()
+            .filter_map(|doc| serde_json::from_value(serde_json::to_value(doc).ok()?).ok())
+            .collect();
+        let vector = self.sync_state.read().await.local_vector.clone();
+        let seq = existing.last().map(|checkpoint| checkpoint.seq + 1).unwrap_or(0);
+
+        let fresh = Checkpoint::new(seq, vector, documents)?;
+        let fresh_doc = serde_json::from_value(serde_json::to_value(&fresh)?)?;
+        self.storage.insert(&checkpoint_collection, fresh_doc).await?;
+        existing.push(fresh);
+
+        while existing.len() > 2 {
+            let stale = existing.remove(0);
+            self.storage.delete(&checkpoint_collection, &stale.id).await?;
+        }
+
+        if existing.len() == 2 {
+            let safe_boundary = &existing[0];
+            let oplog_name = oplog_collection_name(&self.name);
+            for doc in self.storage.find_all(&oplog_name).await? {
+                let operation: CRDTOperation = match serde_json::from_value(serde_json::to_value(&doc)?) {
+                    Ok(operation) => operation,
+                    Err(_) => continue,
+                };
+                if operation.vector.happens_before(&safe_boundary.vector) {
+                    self.storage.delete(&oplog_name, &operation.id).await?;
+                }
+            }
+        }
+
+        Ok(())
     }
 
     /// Insert inserts a document into the collection
     pub async fn insert(&self, ctx: &str, doc: HashMap<String, serde_json::Value>) -> Result<HashMap<String, serde_json::Value>, Box<dyn std::error::Error + Send + Sync>> {
-        // Add CRDT metadata
-        let mut distributed_doc = DistributedDocument {
+        let vector = self.next_vector().await;
+        let distributed_doc = DistributedDocument {
             id: doc.get("id").and_then(|v| v.as_str()).unwrap_or("").to_string(),
             entry_type: EntryType::Memory, // Default
             payload: Some(doc.clone()),
-            vector: crate::clock::VectorClock::new().increment(self.network.get_peer_id()),
-            timestamp: chrono::Utc::now().timestamp(),
+            vector: vector.clone(),
+            timestamp: self.time_source.now().timestamp(),
             peer_id: self.network.get_peer_id(),
             stage: None,
             deleted: false,
@@ -57,52 +426,110 @@ impl DistributedCollection {
 
         // Store locally
         let storage_doc = serde_json::to_value(&distributed_doc)?;
-        let mut storage_map = serde_json::from_value::<HashMap<String, serde_json::Value>>(storage_doc)?;
+        let storage_map = serde_json::from_value::<HashMap<String, serde_json::Value>>(storage_doc)?;
         self.storage.insert(&self.name, storage_map).await?;
 
-        // Emit CRDT operation if networked
-        if let Some(network_id) = &self.network_id {
-            let operation = CRDTOperation {
-                id: uuid::Uuid::new_v4().to_string(),
-                op_type: OperationType::Insert,
-                collection: self.name.clone(),
-                document_id: distributed_doc.id.clone(),
-                data: Some(distributed_doc),
-                vector: crate::clock::VectorClock::new().increment(self.network.get_peer_id()),
-                timestamp: chrono::Utc::now().timestamp(),
-                peer_id: self.network.get_peer_id(),
-            };
+        let mut operation = CRDTOperation {
+            id: uuid::Uuid::new_v4().to_string(),
+            op_type: OperationType::Insert,
+            collection: self.name.clone(),
+            document_id: distributed_doc.id.clone(),
+            timestamp: distributed_doc.timestamp,
+            peer_id: distributed_doc.peer_id.clone(),
+            data: Some(distributed_doc),
+            payload_ref: None,
+            vector,
+            signature: None,
+        };
+        self.offload_if_large(&mut operation).await?;
+        operation.sign(&self.network.identity());
 
-            let msg = ProtocolMessage {
-                msg_type: MessageType::Operation,
-                network_id: network_id.clone(),
-                sender_id: self.network.get_peer_id(),
-                timestamp: chrono::Utc::now().timestamp(),
-                payload: serde_json::to_value(&operation)?,
-            };
-
-            self.network.broadcast_message(network_id, msg).await?;
-        }
+        self.append_operation(&operation).await?;
+        self.broadcast_operation(&operation).await?;
 
         Ok(doc)
     }
 
     /// Update updates a document in the collection
     pub async fn update(&self, id: &str, update: HashMap<String, serde_json::Value>) -> Result<i32, Box<dyn std::error::Error + Send + Sync>> {
-        if let Some(mut doc) = self.storage.find(&self.name, id).await? {
+        let existing = match self.storage.find(&self.name, id).await? {
+            Some(doc) => doc,
+            None => return Ok(0),
+        };
+
+        let mut distributed_doc: DistributedDocument = serde_json::from_value(serde_json::to_value(&existing)?)?;
+        if let Some(payload) = distributed_doc.payload.as_mut() {
             for (k, v) in update {
-                doc.insert(k, v);
+                payload.insert(k, v);
             }
-            self.storage.insert(&self.name, doc).await?;
-            Ok(1)
         } else {
-            Ok(0)
+            distributed_doc.payload = Some(update);
         }
+        distributed_doc.vector = self.next_vector().await;
+        distributed_doc.timestamp = self.time_source.now().timestamp();
+        distributed_doc.peer_id = self.network.get_peer_id();
+
+        let storage_doc = serde_json::from_value(serde_json::to_value(&distributed_doc)?)?;
+        self.storage.insert(&self.name, storage_doc).await?;
+
+        let mut operation = CRDTOperation {
+            id: uuid::Uuid::new_v4().to_string(),
+            op_type: OperationType::Update,
+            collection: self.name.clone(),
+            document_id: distributed_doc.id.clone(),
+            vector: distributed_doc.vector.clone(),
+            timestamp: distributed_doc.timestamp,
+            peer_id: distributed_doc.peer_id.clone(),
+            data: Some(distributed_doc),
+            payload_ref: None,
+            signature: None,
+        };
+        self.offload_if_large(&mut operation).await?;
+        operation.sign(&self.network.identity());
+
+        self.append_operation(&operation).await?;
+        self.broadcast_operation(&operation).await?;
+        self.gc_pending_proposal(id).await;
+
+        Ok(1)
     }
 
     /// Delete deletes a document from the collection
     pub async fn delete(&self, id: &str) -> Result<i32, Box<dyn std::error::Error + Send + Sync>> {
+        let existing: Option<DistributedDocument> = self.storage.find(&self.name, id).await?
+            .and_then(|doc| serde_json::from_value(serde_json::to_value(doc).ok()?).ok());
         self.storage.delete(&self.name, id).await?;
+
+        let vector = self.next_vector().await;
+        let tombstone = DistributedDocument {
+            id: id.to_string(),
+            entry_type: existing.map(|doc| doc.entry_type).unwrap_or(EntryType::Memory),
+            payload: None,
+            vector: vector.clone(),
+            timestamp: self.time_source.now().timestamp(),
+            peer_id: self.network.get_peer_id(),
+            stage: None,
+            deleted: true,
+        };
+
+        let mut operation = CRDTOperation {
+            id: uuid::Uuid::new_v4().to_string(),
+            op_type: OperationType::Delete,
+            collection: self.name.clone(),
+            document_id: tombstone.id.clone(),
+            timestamp: tombstone.timestamp,
+            peer_id: tombstone.peer_id.clone(),
+            data: Some(tombstone),
+            payload_ref: None,
+            vector,
+            signature: None,
+        };
+        operation.sign(&self.network.identity());
+
+        self.append_operation(&operation).await?;
+        self.broadcast_operation(&operation).await?;
+        self.gc_pending_proposal(id).await;
+
         Ok(1)
     }
 
@@ -136,12 +563,186 @@ impl DistributedCollection {
         Ok(())
     }
 
-    /// ForceSync forces a synchronization of the collection
+    /// ForceSync recovers the collection's materialized state from its
+    /// operation log and checkpoints: the newest checkpoint that still
+    /// passes its checksum is used as a base (falling back to the next
+    /// older one, or to a full replay from empty state if none validate —
+    /// see `Checkpoint::is_valid`), then every logged operation not already
+    /// reflected in that checkpoint's vector clock is folded in through
+    /// `CRDTResolver` in deterministic `(timestamp, peer_id)` order, and the
+    /// result is written back to storage.
     pub async fn force_sync(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        // Simplified sync implementation
+        {
+            let mut sync_state = self.sync_state.write().await;
+            sync_state.sync_in_progress = true;
+        }
+
+        let mut checkpoints: Vec<Checkpoint> = self.storage.find_all(&checkpoint_collection_name(&self.name)).await?
+            .into_iter()
+            .filter_map(|doc| serde_json::from_value(serde_json::to_value(doc).ok()?).ok())
+            .collect();
+        checkpoints.sort_by(|a, b| b.seq.cmp(&a.seq));
+
+        let base = checkpoints.into_iter().find(Checkpoint::is_valid);
+        let (base_vector, mut materialized): (VectorClock, HashMap<String, DistributedDocument>) = match base {
+            Some(checkpoint) => (
+                checkpoint.vector,
+                checkpoint.documents.into_iter().map(|doc| (doc.id.clone(), doc)).collect(),
+            ),
+            None => (VectorClock::new(), HashMap::new()),
+        };
+
+        let mut operations: Vec<CRDTOperation> = self.storage.find_all(&oplog_collection_name(&self.name)).await?
+            .into_iter()
+            .filter_map(|doc| serde_json::from_value(serde_json::to_value(doc).ok()?).ok())
+            .filter(|operation: &CRDTOperation| !operation.vector.happens_before(&base_vector))
+            .collect();
+        operations.sort_by(|a, b| a.timestamp.cmp(&b.timestamp).then_with(|| a.peer_id.cmp(&b.peer_id)));
+
+        for operation in operations {
+            let incoming = match operation.data {
+                Some(data) => data,
+                None => continue,
+            };
+            let incoming = match self.resolve_payload_ref(incoming, &operation.payload_ref).await {
+                Some(incoming) => incoming,
+                None => continue, // offloaded payload not held locally; skip until a later sync resolves it
+            };
+
+            match materialized.remove(&incoming.id) {
+                Some(current) => {
+                    let merged = self.resolver.merge_documents(&current, &incoming);
+                    materialized.insert(merged.id.clone(), merged);
+                }
+                None => {
+                    materialized.insert(incoming.id.clone(), incoming);
+                }
+            }
+        }
+
+        for document in materialized.values() {
+            let storage_doc = serde_json::from_value(serde_json::to_value(document)?)?;
+            self.storage.insert(&self.name, storage_doc).await?;
+        }
+
         let mut sync_state = self.sync_state.write().await;
-        sync_state.last_sync = chrono::Utc::now();
+        sync_state.last_sync = self.time_source.now();
         sync_state.sync_in_progress = false;
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::handshake::LongTermIdentity;
+    use crate::storage::file::FileStorage;
+
+    /// MockNetwork stands in for `Network` for collections that are never
+    /// attached to a real network (`network_id` stays `None`), so only
+    /// `get_peer_id`/`identity` — which `insert`/`update` call unconditionally
+    /// — need to actually do anything.
+    struct MockNetwork {
+        peer_id: String,
+        identity: Arc<LongTermIdentity>,
+    }
+
+    impl MockNetwork {
+        fn new() -> Self {
+            MockNetwork {
+                peer_id: uuid::Uuid::new_v4().to_string(),
+                identity: Arc::new(LongTermIdentity::generate()),
+            }
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl Network for MockNetwork {
+        async fn initialize(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> { Ok(()) }
+        async fn create_network(&self, _cfg: NetworkConfig) -> Result<String, Box<dyn std::error::Error + Send + Sync>> { unimplemented!() }
+        async fn join_network(&self, _network_id: &str, _bootstrap_peers: Vec<String>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> { unimplemented!() }
+        async fn leave_network(&self, _network_id: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> { unimplemented!() }
+        async fn add_collection_to_network(&self, _network_id: &str, _collection_name: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> { unimplemented!() }
+        async fn remove_collection_from_network(&self, _network_id: &str, _collection_name: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> { unimplemented!() }
+        fn get_network_collections(&self, _network_id: &str) -> Vec<String> { vec![] }
+        fn network_id_for_collection(&self, _collection_name: &str) -> Option<String> { None }
+        async fn broadcast_message(&self, _network_id: &str, _msg: ProtocolMessage) -> Result<(), Box<dyn std::error::Error + Send + Sync>> { Ok(()) }
+        async fn send_to_peer(&self, _peer_id: &str, _network_id: &str, _msg: ProtocolMessage) -> Result<(), Box<dyn std::error::Error + Send + Sync>> { Ok(()) }
+        fn on_message(&self, _mt: MessageType, _handler: MessageHandler) {}
+        async fn request(&self, _peer_id: &str, _network_id: &str, _msg: ProtocolMessage) -> Result<ProtocolMessage, Box<dyn std::error::Error + Send + Sync>> { unimplemented!() }
+        async fn sample_peers(&self, _network_id: &str, _n: usize) -> Vec<PeerInfo> { vec![] }
+        fn get_network_stats(&self, _network_id: &str) -> Option<NetworkStats> { None }
+        fn get_networks(&self) -> Vec<NetworkConfig> { vec![] }
+        fn get_peer_id(&self) -> String { self.peer_id.clone() }
+        fn get_peers(&self) -> Vec<PeerInfo> { vec![] }
+        fn identity(&self) -> Arc<LongTermIdentity> { Arc::clone(&self.identity) }
+        fn negotiated_codec(&self, _peer_id: &str) -> crate::codec::CodecKind { crate::codec::CodecKind::Json }
+        async fn shutdown(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> { Ok(()) }
+    }
+
+    fn test_collection() -> (DistributedCollection, std::path::PathBuf) {
+        let base_dir = std::env::temp_dir().join(format!("knirvbase_collection_test_{}", uuid::Uuid::new_v4()));
+        let storage: Arc<dyn Storage> = Arc::new(FileStorage::new(base_dir.to_string_lossy().into_owned()).unwrap());
+        let network: Arc<dyn Network> = Arc::new(MockNetwork::new());
+        (DistributedCollection::new("widgets".to_string(), storage, network), base_dir)
+    }
+
+    /// Inserting past `CHECKPOINT_INTERVAL` operations must trigger
+    /// `checkpoint`, which writes a fresh snapshot and compacts the oplog
+    /// down to the fallback checkpoint's vector clock rather than letting it
+    /// grow without bound.
+    #[tokio::test]
+    async fn append_operation_checkpoints_and_compacts_the_oplog() {
+        let (collection, base_dir) = test_collection();
+
+        for i in 0..(CHECKPOINT_INTERVAL + 5) {
+            let mut doc = HashMap::new();
+            doc.insert("id".to_string(), serde_json::Value::String(format!("doc-{}", i)));
+            doc.insert("value".to_string(), serde_json::json!(i));
+            collection.insert("", doc).await.unwrap();
+        }
+
+        let checkpoints = collection.storage.find_all(&checkpoint_collection_name(&collection.name)).await.unwrap();
+        assert!(!checkpoints.is_empty(), "expected at least one checkpoint to have been written");
+
+        let oplog_len = collection.storage.find_all(&oplog_collection_name(&collection.name)).await.unwrap().len();
+        assert!(oplog_len < CHECKPOINT_INTERVAL + 5, "oplog should have been compacted, found {} entries", oplog_len);
+
+        let _ = std::fs::remove_dir_all(&base_dir);
+    }
+
+    /// force_sync must reconstruct the same set of documents from a
+    /// checkpoint plus the operations that happened after it as are already
+    /// sitting in `self.name`'s live storage — the whole point of the
+    /// checkpoint/replay path is that it's a no-op observably.
+    #[tokio::test]
+    async fn force_sync_reconstructs_materialized_state_from_checkpoint_and_replay() {
+        let (collection, base_dir) = test_collection();
+
+        for i in 0..(CHECKPOINT_INTERVAL + 3) {
+            let mut doc = HashMap::new();
+            doc.insert("id".to_string(), serde_json::Value::String(format!("doc-{}", i)));
+            doc.insert("value".to_string(), serde_json::json!(i));
+            collection.insert("", doc).await.unwrap();
+        }
+
+        let before: Vec<DistributedDocument> = collection.storage.find_all(&collection.name).await.unwrap()
+            .into_iter()
+            .filter_map(|doc| serde_json::from_value(serde_json::to_value(doc).ok()?).ok())
+            .collect();
+
+        collection.force_sync().await.unwrap();
+
+        let mut after: Vec<DistributedDocument> = collection.storage.find_all(&collection.name).await.unwrap()
+            .into_iter()
+            .filter_map(|doc| serde_json::from_value(serde_json::to_value(doc).ok()?).ok())
+            .collect();
+
+        let mut before_sorted = before;
+        before_sorted.sort_by(|a, b| a.id.cmp(&b.id));
+        after.sort_by(|a, b| a.id.cmp(&b.id));
+        assert_eq!(before_sorted, after);
+
+        let _ = std::fs::remove_dir_all(&base_dir);
+    }
+}
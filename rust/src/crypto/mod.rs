@@ -0,0 +1,5 @@
+pub mod pqc;
+pub mod handshake;
+pub mod boxstream;
+pub mod threshold;
+pub mod signable;
@@ -3,12 +3,61 @@
 
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 use parking_lot::RwLock;
 use aes_gcm::{Aes256Gcm, Key, Nonce, KeyInit};
 use aes_gcm::aead::Aead;
 use sha2::{Sha256, Digest};
 use base64::{Engine as _, engine::general_purpose};
+use crate::codec;
+use crate::crypto::threshold::{self, Share};
+use crate::network::Network;
+use crate::storage::Storage;
+use crate::time::{SystemTimeSource, TimeSource};
+use crate::types::{MessageType, ProtocolMessage};
+
+/// Storage collection holding, per key id, the next nonce counter to use for
+/// that key (see `NonceState`) — persisted on every encryption so a process
+/// restart never reuses a nonce under the same key.
+const NONCE_COUNTER_COLLECTION: &str = "_nonce_counters";
+
+/// Storage collection recording, per retired key id, the successor it was
+/// rotated to (see `EncryptionManager::rotate_key`) — persisted so key
+/// lineage survives a restart.
+const KEY_LINEAGE_COLLECTION: &str = "_key_lineage";
+
+/// Default key-rotation policy thresholds (see `RotationPolicy`).
+const DEFAULT_MAX_KEY_AGE_SECS: i64 = 30 * 24 * 60 * 60; // 30 days
+const DEFAULT_MAX_MESSAGES_PER_KEY: u64 = 1_000_000;
+
+/// Version tag for `ShareRecord`'s on-disk format, so a future change to the
+/// record layout can be detected before blindly attempting reconstruction.
+pub const SHARE_RECORD_VERSION: u32 = 1;
+
+/// ShareRecord is one shareholder's persisted share of a master `PQCKeyPair`,
+/// produced by `PQCKeyPair::split_into_shares`. See that method and
+/// `FileStorage::reconstruct_master_key` for how it's produced and consumed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShareRecord {
+    pub version: u32,
+    pub id: String,
+    pub key_id: String,
+    pub name: String,
+    pub purpose: String,
+    pub algorithm: String,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub status: String,
+    pub public_key: Vec<u8>,
+    /// Threshold `k` required to reconstruct the key this share belongs to.
+    pub threshold: u8,
+    pub index: u8,
+    pub data: Vec<u8>,
+    /// SHA-256 hash (hex) of the unsplit private key, carried on every share
+    /// so a reconstruction attempt can be verified before being trusted.
+    pub commitment: String,
+}
 
 /// PQCKeyPair represents a complete PQC key pair with both Kyber and Dilithium keys
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -29,6 +78,13 @@ pub struct PQCKeyPair {
 impl PQCKeyPair {
     /// GeneratePQCKeyPair generates a new PQC key pair
     pub fn generate(name: String, purpose: String) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        Self::generate_at(name, purpose, Utc::now())
+    }
+
+    /// generate_at is `generate` with an injected creation time, so
+    /// `EncryptionManager` can stamp new key pairs using its `TimeSource`
+    /// instead of the wall clock, keeping rotation deterministic in tests.
+    pub fn generate_at(name: String, purpose: String, now: DateTime<Utc>) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
         // Generate unique ID
         let id = uuid::Uuid::new_v4().to_string();
 
@@ -43,7 +99,7 @@ impl PQCKeyPair {
             name,
             purpose,
             algorithm: "Mock-PQC".to_string(),
-            created_at: Utc::now(),
+            created_at: now,
             expires_at: None,
             status: "active".to_string(),
             public_key,
@@ -70,14 +126,51 @@ impl PQCKeyPair {
         Ok(serde_json::to_vec(self)?)
     }
 
-    /// Encrypt encrypts data using AES-256-GCM (simplified PQC encryption)
-    pub fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
-        aes_encrypt(&self.public_key, plaintext)
+    /// split_into_shares splits this key pair's private key into `n`
+    /// threshold secret-sharing shares requiring `k` to reconstruct (see
+    /// `crate::crypto::threshold`), each wrapped as a `ShareRecord` carrying
+    /// enough metadata — public key, algorithm, a commitment hash of the
+    /// unsplit private key — to rebuild the full `PQCKeyPair` from any `k` of
+    /// them via `FileStorage::reconstruct_master_key`. Each record is meant
+    /// to be persisted as an ordinary document — one per distinct peer — in
+    /// the `pqc_keys` collection, so no single peer's storage holds enough
+    /// shares to recover the key on its own.
+    pub fn split_into_shares(&self, k: u8, n: u8) -> Result<Vec<ShareRecord>, Box<dyn std::error::Error + Send + Sync>> {
+        let shares = threshold::split_secret(&self.private_key, k, n)?;
+        let commitment = commitment_hash(&self.private_key);
+
+        Ok(shares.into_iter().map(|share| ShareRecord {
+            version: SHARE_RECORD_VERSION,
+            id: format!("{}_share_{}", self.id, share.index),
+            key_id: self.id.clone(),
+            name: self.name.clone(),
+            purpose: self.purpose.clone(),
+            algorithm: self.algorithm.clone(),
+            created_at: self.created_at,
+            expires_at: self.expires_at,
+            status: self.status.clone(),
+            public_key: self.public_key.clone(),
+            threshold: k,
+            index: share.index,
+            data: share.data,
+            commitment: commitment.clone(),
+        }).collect())
+    }
+
+    /// Encrypt encrypts data using AES-256-GCM (simplified PQC encryption),
+    /// binding `aad` as additional authenticated data so a caller can catch
+    /// tampering with out-of-band metadata describing the ciphertext (e.g.
+    /// the envelope's `key_id`/`algorithm`) by passing the same `aad` to
+    /// `decrypt`.
+    pub fn encrypt(&self, plaintext: &[u8], aad: &[u8], nonce: &[u8; 12]) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+        aes_encrypt(&self.private_key, plaintext, aad, nonce)
     }
 
-    /// Decrypt decrypts data using AES-256-GCM (simplified PQC decryption)
-    pub fn decrypt(&self, ciphertext: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
-        aes_decrypt(&self.private_key, ciphertext)
+    /// Decrypt decrypts data using AES-256-GCM (simplified PQC decryption).
+    /// `aad` must match what was passed to `encrypt` or the GCM tag check
+    /// fails.
+    pub fn decrypt(&self, ciphertext: &[u8], aad: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+        aes_decrypt(&self.private_key, ciphertext, aad)
     }
 
     /// Sign signs data (simplified signature)
@@ -95,8 +188,14 @@ impl PQCKeyPair {
 
     /// IsExpired checks if the key pair has expired
     pub fn is_expired(&self) -> bool {
+        self.is_expired_at(Utc::now())
+    }
+
+    /// is_expired_at is `is_expired` judged against an injected `now`
+    /// instead of the wall clock. See `generate_at`.
+    pub fn is_expired_at(&self, now: DateTime<Utc>) -> bool {
         if let Some(expires_at) = self.expires_at {
-            Utc::now() > expires_at
+            now > expires_at
         } else {
             false
         }
@@ -104,7 +203,88 @@ impl PQCKeyPair {
 
     /// IsActive checks if the key pair is active and not expired
     pub fn is_active(&self) -> bool {
-        self.status == "active" && !self.is_expired()
+        self.is_active_at(Utc::now())
+    }
+
+    /// is_active_at is `is_active` judged against an injected `now` instead
+    /// of the wall clock. See `generate_at`.
+    pub fn is_active_at(&self, now: DateTime<Utc>) -> bool {
+        self.status == "active" && !self.is_expired_at(now)
+    }
+}
+
+/// NonceState is the per-key nonce counter backing AES-GCM nonce
+/// construction: the nonce for a message is `salt || counter`, where `salt`
+/// is a random 4-byte prefix fixed for the key's lifetime and `counter` is a
+/// 64-bit value that increments on every encryption and is never reused.
+/// Mirrors the monotonic-counter nonce scheme VPNCloud's crypto core uses to
+/// rule out the birthday-bound nonce collisions a purely random 96-bit nonce
+/// risks under high-volume encryption.
+#[derive(Debug, Clone, Copy)]
+struct NonceState {
+    salt: [u8; 4],
+    counter: u64,
+}
+
+impl NonceState {
+    fn fresh() -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let mut salt = [0u8; 4];
+        getrandom::getrandom(&mut salt)?;
+        Ok(NonceState { salt, counter: 0 })
+    }
+
+    fn nonce_bytes(&self) -> [u8; 12] {
+        let mut nonce = [0u8; 12];
+        nonce[..4].copy_from_slice(&self.salt);
+        nonce[4..].copy_from_slice(&self.counter.to_be_bytes());
+        nonce
+    }
+
+    fn to_doc(self, key_id: &str) -> HashMap<String, serde_json::Value> {
+        let mut doc = HashMap::new();
+        doc.insert("id".to_string(), serde_json::Value::String(key_id.to_string()));
+        doc.insert("salt".to_string(), serde_json::json!(self.salt));
+        doc.insert("counter".to_string(), serde_json::json!(self.counter));
+        doc
+    }
+
+    fn from_doc(doc: &HashMap<String, serde_json::Value>) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let salt_vec: Vec<u8> = doc.get("salt").cloned()
+            .map(serde_json::from_value)
+            .transpose()?
+            .ok_or("nonce counter record is missing salt")?;
+        if salt_vec.len() != 4 {
+            return Err("nonce counter record has a malformed salt".into());
+        }
+        let mut salt = [0u8; 4];
+        salt.copy_from_slice(&salt_vec);
+
+        let counter = doc.get("counter").and_then(|v| v.as_u64())
+            .ok_or("nonce counter record is missing counter")?;
+
+        Ok(NonceState { salt, counter })
+    }
+}
+
+/// RotationPolicy controls when a key is due for rotation (see
+/// `EncryptionManager::rotate_key`): once a key has been active for longer
+/// than `max_age`, or has encrypted at least `max_messages` messages (judged
+/// from its nonce counter — see `reserve_nonce`), the next `encrypt_data`
+/// call against it rotates to a fresh successor before encrypting. Either
+/// threshold can be disabled with `None`. Mirrors VPNCloud's periodic key
+/// roll, which rotates on the same two conditions.
+#[derive(Debug, Clone, Copy)]
+pub struct RotationPolicy {
+    pub max_age: Option<chrono::Duration>,
+    pub max_messages: Option<u64>,
+}
+
+impl Default for RotationPolicy {
+    fn default() -> Self {
+        RotationPolicy {
+            max_age: Some(chrono::Duration::seconds(DEFAULT_MAX_KEY_AGE_SECS)),
+            max_messages: Some(DEFAULT_MAX_MESSAGES_PER_KEY),
+        }
     }
 }
 
@@ -112,15 +292,144 @@ impl PQCKeyPair {
 pub struct EncryptionManager {
     master_key: RwLock<Option<PQCKeyPair>>,
     key_cache: RwLock<HashMap<String, PQCKeyPair>>,
+    nonce_states: RwLock<HashMap<String, NonceState>>,
+    rotation_policy: RwLock<RotationPolicy>,
+    time_source: RwLock<Arc<dyn TimeSource>>,
 }
 
 impl EncryptionManager {
-    /// NewEncryptionManager creates a new encryption manager
+    /// NewEncryptionManager creates a new encryption manager, backed by the
+    /// real wall clock (`SystemTimeSource`). Use `set_time_source` to swap
+    /// in a `MockTimeSource` for deterministic rotation/expiry tests.
     pub fn new() -> Self {
         EncryptionManager {
             master_key: RwLock::new(None),
             key_cache: RwLock::new(HashMap::new()),
+            nonce_states: RwLock::new(HashMap::new()),
+            rotation_policy: RwLock::new(RotationPolicy::default()),
+            time_source: RwLock::new(Arc::new(SystemTimeSource)),
+        }
+    }
+
+    /// SetTimeSource swaps in the given `TimeSource`, e.g. a
+    /// `MockTimeSource` in tests that need to control expiry/rotation
+    /// timing exactly.
+    pub fn set_time_source(&self, time_source: Arc<dyn TimeSource>) {
+        *self.time_source.write() = time_source;
+    }
+
+    /// now returns the current time according to this manager's
+    /// `TimeSource`.
+    fn now(&self) -> DateTime<Utc> {
+        self.time_source.read().now()
+    }
+
+    /// SetRotationPolicy replaces the policy `needs_rotation` checks
+    /// `encrypt_data` calls against (see `RotationPolicy`).
+    pub fn set_rotation_policy(&self, policy: RotationPolicy) {
+        *self.rotation_policy.write() = policy;
+    }
+
+    /// needs_rotation reports whether `key_pair` has crossed the current
+    /// rotation policy's age or message-count threshold.
+    fn needs_rotation(&self, key_pair: &PQCKeyPair) -> bool {
+        let policy = *self.rotation_policy.read();
+
+        if let Some(max_age) = policy.max_age {
+            if self.now() - key_pair.created_at > max_age {
+                return true;
+            }
+        }
+
+        if let Some(max_messages) = policy.max_messages {
+            if let Some(state) = self.nonce_states.read().get(&key_pair.id) {
+                if state.counter >= max_messages {
+                    return true;
+                }
+            }
+        }
+
+        false
+    }
+
+    /// rotate_key retires `key_id`: it generates a successor `PQCKeyPair`
+    /// with the same name and purpose, marks the retired key pair's status
+    /// "rotated" but keeps it in the cache so ciphertext already encrypted
+    /// under it stays decryptable, and — if `key_id` was the master key —
+    /// promotes the successor to master so every subsequent `encrypt_data`
+    /// call routes to it automatically. The predecessor -> successor
+    /// lineage is persisted via `storage` so rotation survives a restart;
+    /// `re_encrypt`-style migration off the retired key can later walk that
+    /// lineage to decide when it's finally safe to `remove_key` it. Returns
+    /// the successor.
+    pub async fn rotate_key(&self, key_id: &str, storage: &dyn Storage) -> Result<PQCKeyPair, Box<dyn std::error::Error + Send + Sync>> {
+        let mut retired = {
+            let cache = self.key_cache.read();
+            if let Some(kp) = cache.get(key_id) {
+                kp.clone()
+            } else if let Some(master) = self.master_key.read().as_ref() {
+                if master.id == key_id {
+                    master.clone()
+                } else {
+                    return Err(format!("key {} not found in cache", key_id).into());
+                }
+            } else {
+                return Err(format!("key {} not found in cache", key_id).into());
+            }
+        };
+
+        let successor = PQCKeyPair::generate_at(retired.name.clone(), retired.purpose.clone(), self.now())?;
+
+        retired.status = "rotated".to_string();
+        {
+            let mut cache = self.key_cache.write();
+            cache.insert(retired.id.clone(), retired.clone());
+            cache.insert(successor.id.clone(), successor.clone());
         }
+
+        let was_master = self.master_key.read().as_ref().map(|m| m.id == key_id).unwrap_or(false);
+        if was_master {
+            *self.master_key.write() = Some(successor.clone());
+        }
+
+        let mut lineage = HashMap::new();
+        lineage.insert("id".to_string(), serde_json::Value::String(retired.id.clone()));
+        lineage.insert("predecessor".to_string(), serde_json::Value::String(retired.id.clone()));
+        lineage.insert("successor".to_string(), serde_json::Value::String(successor.id.clone()));
+        storage.insert(KEY_LINEAGE_COLLECTION, lineage).await?;
+
+        Ok(successor)
+    }
+
+    /// reserve_nonce hands out the next unused nonce for `key_id`, loading
+    /// its counter from `storage` on first use (so a restart picks up where
+    /// a previous process left off) and persisting the incremented counter
+    /// before returning, so a nonce is never handed out twice even if the
+    /// process crashes immediately after. Fails once the 64-bit counter
+    /// space for `key_id` is exhausted, forcing key rotation rather than
+    /// risking nonce reuse.
+    async fn reserve_nonce(&self, key_id: &str, storage: &dyn Storage) -> Result<[u8; 12], Box<dyn std::error::Error + Send + Sync>> {
+        if !self.nonce_states.read().contains_key(key_id) {
+            let state = match storage.find(NONCE_COUNTER_COLLECTION, key_id).await? {
+                Some(doc) => NonceState::from_doc(&doc)?,
+                None => NonceState::fresh()?,
+            };
+            self.nonce_states.write().entry(key_id.to_string()).or_insert(state);
+        }
+
+        let (nonce, doc) = {
+            let mut states = self.nonce_states.write();
+            let state = states.get_mut(key_id).expect("just loaded or inserted above");
+            if state.counter == u64::MAX {
+                return Err(format!("nonce counter for key {} is exhausted; rotate the key", key_id).into());
+            }
+            let nonce = state.nonce_bytes();
+            state.counter += 1;
+            (nonce, state.to_doc(key_id))
+        };
+
+        storage.insert(NONCE_COUNTER_COLLECTION, doc).await?;
+        Ok(nonce)
     }
 
     /// SetMasterKey sets the master PQC key pair for encryption
@@ -133,8 +442,10 @@ impl EncryptionManager {
         self.master_key.read().clone()
     }
 
-    /// EncryptData encrypts sensitive data using PQC encryption
-    pub fn encrypt_data(&self, plaintext: &[u8], key_id: &str) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+    /// EncryptData encrypts sensitive data using PQC encryption. `storage` is
+    /// used to persist this key's nonce counter (see `reserve_nonce`) so
+    /// nonces stay unique across restarts.
+    pub async fn encrypt_data(&self, plaintext: &[u8], key_id: &str, storage: &dyn Storage) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
         let key_pair = {
             let cache = self.key_cache.read();
             if let Some(kp) = cache.get(key_id) {
@@ -150,35 +461,48 @@ impl EncryptionManager {
             }
         };
 
-        if !key_pair.is_active() {
+        if !key_pair.is_active_at(self.now()) {
             return Err(format!("key {} is not active", key_id).into());
         }
 
-        // Encrypt the data
-        let ciphertext = key_pair.encrypt(plaintext)?;
-
-        // Create encrypted payload with metadata
-        let payload = serde_json::json!({
-            "key_id": key_id,
-            "algorithm": "AES-256-GCM",
-            "ciphertext": general_purpose::STANDARD.encode(&ciphertext),
-        });
+        // Rotate transparently once the key crosses its policy threshold,
+        // and encrypt under the successor instead: the caller asked for
+        // whatever key is currently active under `key_id`, and after
+        // rotation that's the successor, not the now-retired original (see
+        // `rotate_key`).
+        let key_pair = if self.needs_rotation(&key_pair) {
+            self.rotate_key(key_id, storage).await?
+        } else {
+            key_pair
+        };
 
-        // Sign the payload for integrity
-        let payload_bytes = serde_json::to_vec(&payload)?;
-        let signature = key_pair.sign(&payload_bytes)?;
+        // Bind the envelope metadata as AES-GCM additional authenticated
+        // data rather than wrapping it in a separate signature: tampering
+        // with `key_id` or `algorithm` then fails the GCM tag check in
+        // `decrypt_data` instead of going unnoticed (`PQCKeyPair::verify` is
+        // a stub that always returns true, so a signature bought nothing).
+        let algorithm = "AES-256-GCM";
+        let aad = envelope_aad(&key_pair.id, algorithm);
+        let nonce = self.reserve_nonce(&key_pair.id, storage).await?;
+        let ciphertext = key_pair.encrypt(plaintext, &aad, &nonce)?;
 
-        // Create final encrypted structure
         let encrypted = serde_json::json!({
-            "payload": payload,
-            "signature": general_purpose::STANDARD.encode(&signature),
+            "key_id": key_pair.id,
+            "algorithm": algorithm,
+            "ciphertext": general_purpose::STANDARD.encode(&ciphertext),
         });
 
         let final_bytes = serde_json::to_vec(&encrypted)?;
         Ok(general_purpose::STANDARD.encode(final_bytes))
     }
 
-    /// DecryptData decrypts data encrypted with EncryptData
+    /// DecryptData decrypts data encrypted with EncryptData. Any alteration
+    /// of `key_id`/`algorithm` since encryption fails the GCM tag check,
+    /// since they're bound in as additional authenticated data. Unlike
+    /// `encrypt_data`, this doesn't require the key to still be active: a
+    /// key `rotate_key` retired stays in the cache precisely so ciphertext
+    /// already encrypted under it (e.g. what `FileStorage::re_encrypt` is
+    /// migrating off of) stays decryptable.
     pub fn decrypt_data(&self, encrypted_data: &str) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
         // Decode the base64 encrypted data
         let encrypted_bytes = general_purpose::STANDARD.decode(encrypted_data)?;
@@ -186,19 +510,12 @@ impl EncryptionManager {
         // Unmarshal the encrypted structure
         let encrypted: serde_json::Value = serde_json::from_slice(&encrypted_bytes)?;
 
-        let payload = encrypted["payload"].clone();
-        let signature_b64 = encrypted["signature"].as_str()
-            .ok_or("missing signature in encrypted data")?;
-        let signature = general_purpose::STANDARD.decode(signature_b64)?;
-
-        // Extract payload
-        let payload_bytes = serde_json::to_vec(&payload)?;
-        let payload_map: serde_json::Value = serde_json::from_slice(&payload_bytes)?;
-
-        let key_id = payload_map["key_id"].as_str()
-            .ok_or("missing key_id in payload")?;
-        let ciphertext_b64 = payload_map["ciphertext"].as_str()
-            .ok_or("missing ciphertext in payload")?;
+        let key_id = encrypted["key_id"].as_str()
+            .ok_or("missing key_id in encrypted data")?;
+        let algorithm = encrypted["algorithm"].as_str()
+            .ok_or("missing algorithm in encrypted data")?;
+        let ciphertext_b64 = encrypted["ciphertext"].as_str()
+            .ok_or("missing ciphertext in encrypted data")?;
         let ciphertext = general_purpose::STANDARD.decode(ciphertext_b64)?;
 
         // Get the key pair
@@ -217,17 +534,8 @@ impl EncryptionManager {
             }
         };
 
-        if !key_pair.is_active() {
-            return Err(format!("key {} is not active", key_id).into());
-        }
-
-        // Verify signature
-        if !key_pair.verify(&payload_bytes, &signature) {
-            return Err("signature verification failed".into());
-        }
-
-        // Decrypt the data
-        key_pair.decrypt(&ciphertext)
+        let aad = envelope_aad(key_id, algorithm);
+        key_pair.decrypt(&ciphertext, &aad)
     }
 
     /// CacheKey adds a key pair to the cache
@@ -242,14 +550,129 @@ impl EncryptionManager {
 
     /// GenerateDataEncryptionKey generates a new key pair for data encryption
     pub fn generate_data_encryption_key(&self, name: String) -> Result<PQCKeyPair, Box<dyn std::error::Error + Send + Sync>> {
-        let key_pair = PQCKeyPair::generate(name, "encryption".to_string())?;
+        let key_pair = PQCKeyPair::generate_at(name, "encryption".to_string(), self.now())?;
         self.cache_key(key_pair.id.clone(), key_pair.clone());
         Ok(key_pair)
     }
+
+    /// decrypt_with_key_bytes decrypts data produced by `encrypt_data` using
+    /// raw key bytes directly — e.g. a key just reconstructed from threshold
+    /// shares — rather than a cached `PQCKeyPair`, so no key lookup by the
+    /// envelope's `key_id` happens. The envelope's `key_id`/`algorithm` are
+    /// still bound as additional authenticated data, exactly as in
+    /// `decrypt_data`.
+    fn decrypt_with_key_bytes(&self, key_bytes: &[u8], encrypted_data: &str) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+        let encrypted_bytes = general_purpose::STANDARD.decode(encrypted_data)?;
+        let encrypted: serde_json::Value = serde_json::from_slice(&encrypted_bytes)?;
+
+        let key_id = encrypted["key_id"].as_str()
+            .ok_or("missing key_id in encrypted data")?;
+        let algorithm = encrypted["algorithm"].as_str()
+            .ok_or("missing algorithm in encrypted data")?;
+        let ciphertext_b64 = encrypted["ciphertext"].as_str()
+            .ok_or("missing ciphertext in encrypted data")?;
+        let ciphertext = general_purpose::STANDARD.decode(ciphertext_b64)?;
+
+        let aad = envelope_aad(key_id, algorithm);
+        aes_decrypt(key_bytes, &ciphertext, &aad)
+    }
+
+    /// start_decryption_session reconstructs a master key split via
+    /// threshold secret sharing (see `crate::crypto::threshold`) and uses it
+    /// to decrypt `encrypted_data`. It asks each of `holders` in turn for
+    /// the share it holds for `key_id`, stopping once `k` distinct, valid
+    /// shares have been collected; a holder that doesn't respond (offline,
+    /// unreachable, or simply never received a share for this key) is
+    /// skipped rather than failing the session outright. A share at index 0,
+    /// or a second share repeating an index already collected, is rejected
+    /// rather than counted. If fewer than `k` holders answer, the session
+    /// fails cleanly without attempting reconstruction. The reconstructed
+    /// key is zeroized before returning, whether or not decryption succeeds.
+    pub async fn start_decryption_session(
+        &self,
+        key_id: &str,
+        k: u8,
+        holders: &[String],
+        network: &dyn Network,
+        encrypted_data: &str,
+    ) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+        let mut shares: Vec<Share> = Vec::new();
+        let mut seen_indices = HashSet::new();
+
+        for holder in holders {
+            if shares.len() >= k as usize {
+                break;
+            }
+
+            let content_codec = network.negotiated_codec(holder);
+            let request_msg = ProtocolMessage {
+                msg_type: MessageType::ShareRequest,
+                network_id: String::new(),
+                sender_id: network.get_peer_id(),
+                timestamp: Utc::now().timestamp(),
+                payload: content_codec.encode(&serde_json::json!({ "key_id": key_id }))?,
+                content_codec,
+                request_id: None,
+                signature: None,
+            };
+
+            let reply = match network.request(holder, "", request_msg).await {
+                Ok(reply) => reply,
+                Err(_) => continue, // unreachable or timed out; try the remaining holders
+            };
+            let reply_payload = codec::payload_value(reply.content_codec, &reply.payload);
+
+            let index = match reply_payload.get("index").and_then(|v| v.as_u64()) {
+                Some(i) if i != 0 && i < 255 => i as u8,
+                _ => continue, // index 0 is reserved for the secret; reject it
+            };
+            if !seen_indices.insert(index) {
+                continue; // a repeated index proves nothing new
+            }
+
+            let data = reply_payload.get("data")
+                .and_then(|v| v.as_array())
+                .map(|arr| arr.iter().filter_map(|b| b.as_u64().map(|n| n as u8)).collect::<Vec<u8>>());
+
+            if let Some(data) = data {
+                shares.push(Share { index, data });
+            }
+        }
+
+        if shares.len() < k as usize {
+            return Err(format!("decryption session failed: only {} of {} required shares were returned", shares.len(), k).into());
+        }
+
+        let mut key_bytes = threshold::reconstruct_secret(&shares)?;
+        let result = self.decrypt_with_key_bytes(&key_bytes, encrypted_data);
+        threshold::zeroize(&mut key_bytes);
+        result
+    }
+}
+
+/// commitment_hash hex-encodes the SHA-256 digest of `private_key`, binding
+/// a `ShareRecord` to the exact key it was split from so
+/// `FileStorage::reconstruct_master_key` can catch a bad reconstruction
+/// before trusting it.
+pub(crate) fn commitment_hash(private_key: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(private_key);
+    format!("{:x}", hasher.finalize())
+}
+
+/// envelope_aad builds the additional authenticated data binding an
+/// encrypted envelope's `key_id` and `algorithm` metadata to its ciphertext,
+/// so altering either after encryption fails the GCM tag check instead of
+/// silently going through. See `EncryptionManager::encrypt_data`.
+fn envelope_aad(key_id: &str, algorithm: &str) -> Vec<u8> {
+    format!("{}:{}", key_id, algorithm).into_bytes()
 }
 
-/// AES-256-GCM encryption
-fn aes_encrypt(key: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+/// AES-256-GCM encryption, binding `aad` as additional authenticated data
+/// and using the given 12-byte `nonce` rather than drawing a random one —
+/// callers must never reuse a nonce under the same key. See
+/// `EncryptionManager::reserve_nonce`.
+fn aes_encrypt(key: &[u8], plaintext: &[u8], aad: &[u8], nonce_bytes: &[u8; 12]) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
     // Derive a 32-byte key using SHA-256 if the key is not exactly 32 bytes
     let aes_key = if key.len() == 32 {
         key.to_vec()
@@ -261,20 +684,19 @@ fn aes_encrypt(key: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, Box<dyn std::err
 
     let key = aes_gcm::Key::<Aes256Gcm>::from_slice(&aes_key);
     let cipher = Aes256Gcm::new(key);
+    let nonce = Nonce::from_slice(nonce_bytes);
 
-    let mut nonce_bytes = [0u8; 12];
-    getrandom::getrandom(&mut nonce_bytes)?;
-    let nonce = Nonce::from_slice(&nonce_bytes);
-
-    let ciphertext = cipher.encrypt(nonce, plaintext).map_err(|_| "encryption failed")?;
+    let ciphertext = cipher.encrypt(nonce, aes_gcm::aead::Payload { msg: plaintext, aad })
+        .map_err(|_| "encryption failed")?;
     let mut result = nonce_bytes.to_vec();
     result.extend_from_slice(&ciphertext);
 
     Ok(result)
 }
 
-/// AES-256-GCM decryption
-fn aes_decrypt(key: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+/// AES-256-GCM decryption. `aad` must match what was passed to
+/// `aes_encrypt` or the GCM tag check fails.
+fn aes_decrypt(key: &[u8], ciphertext: &[u8], aad: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
     // Derive a 32-byte key using SHA-256 if the key is not exactly 32 bytes
     let aes_key = if key.len() == 32 {
         key.to_vec()
@@ -294,6 +716,114 @@ fn aes_decrypt(key: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, Box<dyn std::er
     let nonce = Nonce::from_slice(&ciphertext[..12]);
     let ciphertext = &ciphertext[12..];
 
-    cipher.decrypt(nonce, ciphertext)
+    cipher.decrypt(nonce, aes_gcm::aead::Payload { msg: ciphertext, aad })
         .map_err(|_| "decryption failed".into())
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::MockTimeSource;
+    use chrono::Duration;
+
+    /// needs_rotation is gated on EncryptionManager's TimeSource, not the
+    /// wall clock, so a MockTimeSource lets this exercise the age threshold
+    /// deterministically instead of sleeping in the test.
+    #[test]
+    fn needs_rotation_crosses_max_age_threshold_deterministically() {
+        let start = Utc::now();
+        let clock = Arc::new(MockTimeSource::new(start));
+        let manager = EncryptionManager::new();
+        manager.set_time_source(clock.clone());
+        manager.set_rotation_policy(RotationPolicy {
+            max_age: Some(Duration::seconds(60)),
+            max_messages: None,
+        });
+
+        let key_pair = PQCKeyPair::generate_at("test".to_string(), "encryption".to_string(), start).unwrap();
+        assert!(!manager.needs_rotation(&key_pair));
+
+        clock.advance(Duration::seconds(61));
+        assert!(manager.needs_rotation(&key_pair));
+    }
+
+    /// Exercises reserve_nonce's counter persistence and encrypt_data/
+    /// decrypt_data's full round trip against a real Storage, so a
+    /// regression reintroducing the encrypt/decrypt key mismatch (the two
+    /// sides keyed off different buffers) or a nonce-reuse bug in
+    /// reserve_nonce fails a test instead of silently breaking every
+    /// encrypted collection.
+    #[tokio::test]
+    async fn encrypt_data_round_trips_and_never_reuses_a_nonce() {
+        let base_dir = std::env::temp_dir().join(format!("knirvbase_pqc_test_{}", uuid::Uuid::new_v4()));
+        let storage = crate::storage::file::FileStorage::new(base_dir.to_string_lossy().into_owned()).unwrap();
+
+        let manager = EncryptionManager::new();
+        let key_pair = manager.generate_data_encryption_key("test-key".to_string()).unwrap();
+
+        let plaintext = b"the quick brown fox";
+        let encrypted_a = manager.encrypt_data(plaintext, &key_pair.id, &storage).await.unwrap();
+        let encrypted_b = manager.encrypt_data(plaintext, &key_pair.id, &storage).await.unwrap();
+
+        // Same plaintext, same key, but reserve_nonce must hand out distinct
+        // nonces, so the ciphertexts differ even though the inputs match.
+        assert_ne!(encrypted_a, encrypted_b);
+
+        assert_eq!(manager.decrypt_data(&encrypted_a).unwrap(), plaintext);
+        assert_eq!(manager.decrypt_data(&encrypted_b).unwrap(), plaintext);
+
+        let _ = std::fs::remove_dir_all(&base_dir);
+    }
+
+    /// Reconstructing a key from a quorum of its threshold shares
+    /// (`split_into_shares` / `threshold::reconstruct_secret`) must yield
+    /// bytes that actually decrypt data produced by `encrypt_data` under the
+    /// unsplit key — this was dead on arrival while `encrypt`/`decrypt` keyed
+    /// off different buffers (see chunk1-2).
+    #[tokio::test]
+    async fn key_reconstructed_from_threshold_shares_decrypts_data() {
+        let base_dir = std::env::temp_dir().join(format!("knirvbase_pqc_test_{}", uuid::Uuid::new_v4()));
+        let storage = crate::storage::file::FileStorage::new(base_dir.to_string_lossy().into_owned()).unwrap();
+
+        let manager = EncryptionManager::new();
+        let key_pair = manager.generate_data_encryption_key("master".to_string()).unwrap();
+
+        let plaintext = b"shamir-reconstructed key must decrypt this";
+        let encrypted = manager.encrypt_data(plaintext, &key_pair.id, &storage).await.unwrap();
+
+        let shares = key_pair.split_into_shares(3, 5).unwrap();
+        let quorum = &shares[1..4]; // any 3 of the 5 shares should do
+        let quorum_shares: Vec<Share> = quorum.iter()
+            .map(|s| Share { index: s.index, data: s.data.clone() })
+            .collect();
+        let mut reconstructed = threshold::reconstruct_secret(&quorum_shares).unwrap();
+
+        assert_eq!(reconstructed, key_pair.private_key);
+        assert_eq!(manager.decrypt_with_key_bytes(&reconstructed, &encrypted).unwrap(), plaintext);
+
+        threshold::zeroize(&mut reconstructed);
+        let _ = std::fs::remove_dir_all(&base_dir);
+    }
+
+    /// decrypt_data must keep working against a key `rotate_key` has
+    /// already retired: the retired `PQCKeyPair` stays in the cache
+    /// precisely so ciphertext encrypted before rotation stays decryptable,
+    /// and `decrypt_data` (unlike `encrypt_data`) must not reject it just
+    /// because its status is no longer "active".
+    #[tokio::test]
+    async fn decrypt_data_still_works_after_the_key_is_rotated() {
+        let base_dir = std::env::temp_dir().join(format!("knirvbase_pqc_test_{}", uuid::Uuid::new_v4()));
+        let storage = crate::storage::file::FileStorage::new(base_dir.to_string_lossy().into_owned()).unwrap();
+
+        let manager = EncryptionManager::new();
+        let key_pair = manager.generate_data_encryption_key("master".to_string()).unwrap();
+
+        let plaintext = b"encrypted before rotation";
+        let encrypted = manager.encrypt_data(plaintext, &key_pair.id, &storage).await.unwrap();
+
+        manager.rotate_key(&key_pair.id, &storage).await.unwrap();
+
+        assert_eq!(manager.decrypt_data(&encrypted).unwrap(), plaintext);
+
+        let _ = std::fs::remove_dir_all(&base_dir);
+    }
+}
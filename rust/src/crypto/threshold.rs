@@ -0,0 +1,164 @@
+// Shamir's Secret Sharing over GF(256), used to split the master PQC key's
+// private key material across a network's peers so that no single peer can
+// decrypt anything on its own: reconstruction needs `k` of the `n` shares.
+//
+// All arithmetic is over GF(256) with the AES reduction polynomial 0x11B,
+// matching the field AES's S-box and MixColumns already use elsewhere in
+// this codebase's dependency tree. A secret byte `s` is the constant term of
+// a random degree-`(k-1)` polynomial `f(x) = s + a1*x + ... + a_{k-1}*x^{k-1}`;
+// share `i` (for `i` in `1..=n`) is `(i, f(i))`. Index 0 is never handed out
+// since `f(0) = s` would just leak the secret directly. Reconstruction
+// evaluates the unique degree-`(k-1)` polynomial through any `k` shares at
+// `x = 0` via Lagrange interpolation.
+
+use rand::rngs::OsRng;
+use rand::RngCore;
+use std::collections::HashSet;
+
+/// Share is one participant's share of every byte of a shared secret: the
+/// same index `i` is used as the evaluation point `x` for all bytes, so a
+/// participant holds exactly one `(index, data)` pair per secret, however
+/// long that secret is.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Share {
+    pub index: u8,
+    pub data: Vec<u8>,
+}
+
+fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0u8;
+    for _ in 0..8 {
+        if b & 1 != 0 {
+            product ^= a;
+        }
+        let carry = a & 0x80;
+        a <<= 1;
+        if carry != 0 {
+            a ^= 0x1B;
+        }
+        b >>= 1;
+    }
+    product
+}
+
+fn gf_pow(a: u8, mut n: u8) -> u8 {
+    let mut result = 1u8;
+    let mut base = a;
+    while n > 0 {
+        if n & 1 != 0 {
+            result = gf_mul(result, base);
+        }
+        base = gf_mul(base, base);
+        n >>= 1;
+    }
+    result
+}
+
+/// gf_inv returns the multiplicative inverse of `a` in GF(256), using that
+/// every nonzero element has order dividing 255 so `a^254 == a^-1`. `a`
+/// must be nonzero; GF(256) has no inverse for 0.
+fn gf_inv(a: u8) -> u8 {
+    debug_assert!(a != 0, "0 has no multiplicative inverse in GF(256)");
+    gf_pow(a, 254)
+}
+
+fn gf_div(a: u8, b: u8) -> u8 {
+    gf_mul(a, gf_inv(b))
+}
+
+/// split_secret splits `secret` into `n` shares such that any `k` of them
+/// reconstruct it exactly and any `k - 1` reveal nothing about it.
+pub fn split_secret(secret: &[u8], k: u8, n: u8) -> Result<Vec<Share>, Box<dyn std::error::Error + Send + Sync>> {
+    if k == 0 {
+        return Err("threshold k must be at least 1".into());
+    }
+    if k > n {
+        return Err(format!("threshold k={} cannot exceed share count n={}", k, n).into());
+    }
+    if n == 0 || n >= 255 {
+        return Err("share count n must be between 1 and 254 (index 0 is reserved for the secret)".into());
+    }
+
+    // coefficients[byte][j] is a_{j+1} (the random, degree >= 1 coefficients)
+    // for that byte's polynomial; the constant term is the secret byte
+    // itself and is never materialized as a separate "coefficient".
+    let degree = (k - 1) as usize;
+    let mut coefficients: Vec<Vec<u8>> = Vec::with_capacity(secret.len());
+    for _ in secret {
+        let mut coeffs = vec![0u8; degree];
+        OsRng.fill_bytes(&mut coeffs);
+        coefficients.push(coeffs);
+    }
+
+    let mut shares = Vec::with_capacity(n as usize);
+    for index in 1..=n {
+        let mut data = Vec::with_capacity(secret.len());
+        for (byte_idx, &secret_byte) in secret.iter().enumerate() {
+            // Horner's method, high degree to low, with the secret byte as
+            // the final (constant-term) addition.
+            let mut y = 0u8;
+            for &coeff in coefficients[byte_idx].iter().rev() {
+                y = gf_mul(y, index) ^ coeff;
+            }
+            y = gf_mul(y, index) ^ secret_byte;
+            data.push(y);
+        }
+        shares.push(Share { index, data });
+    }
+
+    Ok(shares)
+}
+
+/// reconstruct_secret recovers the original secret from `shares` via
+/// Lagrange interpolation at `x = 0`. Any `k` correct shares (of the `k`
+/// the split required) reconstruct it exactly; passing more than `k` still
+/// works since they all lie on the same polynomial.
+pub fn reconstruct_secret(shares: &[Share]) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+    if shares.is_empty() {
+        return Err("cannot reconstruct a secret from zero shares".into());
+    }
+
+    let secret_len = shares[0].data.len();
+    let mut seen_indices = HashSet::with_capacity(shares.len());
+    for share in shares {
+        if share.index == 0 {
+            return Err("share index 0 is reserved for the secret and must never be used as a share".into());
+        }
+        if !seen_indices.insert(share.index) {
+            return Err(format!("duplicate share index: {}", share.index).into());
+        }
+        if share.data.len() != secret_len {
+            return Err("shares have mismatched lengths".into());
+        }
+    }
+
+    let mut secret = vec![0u8; secret_len];
+    for byte_idx in 0..secret_len {
+        let mut value = 0u8;
+        for (j, share_j) in shares.iter().enumerate() {
+            let mut basis = 1u8;
+            for (m, share_m) in shares.iter().enumerate() {
+                if m == j {
+                    continue;
+                }
+                // Lagrange basis at x=0: product of x_m / (x_m - x_j).
+                // Subtraction is XOR in GF(256).
+                basis = gf_mul(basis, gf_div(share_m.index, share_m.index ^ share_j.index));
+            }
+            value ^= gf_mul(basis, share_j.data[byte_idx]);
+        }
+        secret[byte_idx] = value;
+    }
+
+    Ok(secret)
+}
+
+/// zeroize overwrites `key` with zeros. Best-effort protection against a
+/// reconstructed key lingering in memory after a decryption session; this
+/// codebase has no hardened-memory crate dependency, so this is a plain
+/// overwrite rather than a compiler-fence-guarded one.
+pub fn zeroize(key: &mut [u8]) {
+    for byte in key.iter_mut() {
+        *byte = 0;
+    }
+}
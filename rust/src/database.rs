@@ -5,11 +5,35 @@ use crate::types::*;
 use crate::storage::Storage;
 use crate::network::Network;
 use crate::collection::DistributedCollection;
+use crate::crypto::pqc::{EncryptionManager, PQCKeyPair};
+use crate::crypto::threshold;
+use crate::replication::{ReplicationEngine, ReplicationEngineConfig};
+use crate::post_quorum;
+
+/// Storage collection holding, per master key id, the threshold `k` and the
+/// list of peer ids that were handed a share by `distribute_master_key_shares`
+/// — everything `start_decryption_session` needs to know who to ask. See
+/// `crate::crypto::threshold`.
+pub(crate) const KEY_SHARE_REGISTRY_COLLECTION: &str = "_key_share_registry";
+
+/// Storage collection recording, per post-quorum signing key id, the
+/// commitment hash (see `crypto::pqc::commitment_hash`) shares must
+/// reconstruct to, written once by `bootstrap_post_quorum_key`.
+/// See `crate::post_quorum`.
+pub(crate) const POST_QUORUM_REGISTRY_COLLECTION: &str = "_post_quorum_registry";
 
 /// DistributedDbOptions contains options for the distributed database
 #[derive(Debug, Clone)]
 pub struct DistributedDbOptions {
     pub distributed: DistributedOptions,
+    /// GossipIntervalSecs controls how often the anti-entropy replication
+    /// engine reconciles with peers. Zero is treated as the package default
+    /// (see `replication::DEFAULT_GOSSIP_INTERVAL_SECS`).
+    pub gossip_interval_secs: u64,
+    /// GossipFanout is how many peers each gossip round reconciles against.
+    /// Zero is treated as the package default (see
+    /// `replication::DEFAULT_GOSSIP_FANOUT`).
+    pub gossip_fanout: usize,
 }
 
 #[derive(Debug, Clone)]
@@ -25,6 +49,7 @@ pub struct DistributedDatabase {
     storage: Arc<dyn Storage>,
     collections: Arc<RwLock<HashMap<String, Arc<RwLock<DistributedCollection>>>>>,
     distributed: bool,
+    replication: Arc<ReplicationEngine>,
 }
 
 impl DistributedDatabase {
@@ -36,16 +61,43 @@ impl DistributedDatabase {
     ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
         network.initialize().await?;
 
-        Ok(DistributedDatabase {
+        let replication_config = ReplicationEngineConfig {
+            gossip_interval_secs: if opts.gossip_interval_secs == 0 {
+                crate::replication::DEFAULT_GOSSIP_INTERVAL_SECS
+            } else {
+                opts.gossip_interval_secs
+            },
+            fanout: if opts.gossip_fanout == 0 {
+                crate::replication::DEFAULT_GOSSIP_FANOUT
+            } else {
+                opts.gossip_fanout
+            },
+        };
+        let replication = Arc::new(ReplicationEngine::new(Arc::clone(&network), Arc::clone(&storage), replication_config));
+        let collections = Arc::new(RwLock::new(HashMap::new()));
+        post_quorum::register_handlers(Arc::clone(&network), Arc::clone(&storage), Arc::clone(&collections));
+
+        let db = DistributedDatabase {
             network,
             storage,
-            collections: Arc::new(RwLock::new(HashMap::new())),
+            collections,
             distributed: opts.distributed.enabled,
-        })
+            replication,
+        };
+
+        // Anti-entropy gossip only makes sense once this node is actually
+        // participating in a distributed network.
+        if db.distributed {
+            db.replication.start().await;
+        }
+
+        Ok(db)
     }
 
     /// Collection returns a collection by name
     pub async fn collection(&self, name: &str) -> Arc<RwLock<DistributedCollection>> {
+        self.replication.register_collection(name).await;
+
         let mut collections = self.collections.write().await;
         collections.entry(name.to_string()).or_insert_with(|| {
             Arc::new(RwLock::new(DistributedCollection::new(
@@ -56,6 +108,20 @@ impl DistributedDatabase {
         }).clone()
     }
 
+    /// StartReplication (re)starts the anti-entropy gossip engine. A no-op
+    /// unless `distributed` is enabled — see `DistributedDbOptions`.
+    pub async fn start_replication(&self) {
+        if self.distributed {
+            self.replication.start().await;
+        }
+    }
+
+    /// StopReplication stops the anti-entropy gossip engine. Safe to call
+    /// even if it was never started.
+    pub async fn stop_replication(&self) {
+        self.replication.stop().await;
+    }
+
     /// CreateNetwork creates a new network
     pub async fn create_network(&self, cfg: NetworkConfig) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
         self.network.create_network(cfg).await
@@ -73,6 +139,7 @@ impl DistributedDatabase {
 
     /// Shutdown shuts down the database
     pub async fn shutdown(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.replication.stop().await;
         self.network.shutdown().await
     }
 
@@ -80,4 +147,104 @@ impl DistributedDatabase {
     pub fn raw_network(&self) -> Arc<dyn Network> {
         Arc::clone(&self.network)
     }
+
+    /// distribute_master_key_shares splits `key_pair`'s private key into one
+    /// threshold secret-sharing share per currently known peer (see
+    /// `crate::crypto::threshold`), sends each peer its share via
+    /// `ShareDistribute`, and persists a registry doc recording `k` and the
+    /// holder list so a later `start_decryption_session` knows who to ask.
+    /// Reconstruction will need `k` of the `n` holders to respond.
+    pub async fn distribute_master_key_shares(&self, key_pair: &PQCKeyPair, k: u8) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let peers = self.network.get_peers();
+        let n = peers.len() as u8;
+        if n == 0 {
+            return Err("cannot distribute key shares: no known peers".into());
+        }
+
+        let shares = threshold::split_secret(&key_pair.private_key, k, n)?;
+        let mut holders = Vec::with_capacity(peers.len());
+
+        for (peer, share) in peers.iter().zip(shares.iter()) {
+            let content_codec = self.network.negotiated_codec(&peer.peer_id);
+            let msg = ProtocolMessage {
+                msg_type: MessageType::ShareDistribute,
+                network_id: String::new(),
+                sender_id: self.network.get_peer_id(),
+                timestamp: chrono::Utc::now().timestamp(),
+                payload: content_codec.encode(&serde_json::json!({
+                    "key_id": key_pair.id,
+                    "index": share.index,
+                    "data": share.data,
+                }))?,
+                content_codec,
+                request_id: None,
+                signature: None,
+            };
+            self.network.send_to_peer(&peer.peer_id, "", msg).await?;
+            holders.push(peer.peer_id.clone());
+        }
+
+        let mut doc = HashMap::new();
+        doc.insert("id".to_string(), serde_json::Value::String(key_pair.id.clone()));
+        doc.insert("k".to_string(), serde_json::Value::from(k));
+        doc.insert("holders".to_string(), serde_json::to_value(&holders)?);
+        self.storage.insert(KEY_SHARE_REGISTRY_COLLECTION, doc).await?;
+
+        Ok(())
+    }
+
+    /// start_decryption_session looks up the holder list and threshold `k`
+    /// that `distribute_master_key_shares` recorded for `key_id`, then
+    /// reconstructs the key from its holders' shares and decrypts
+    /// `encrypted_data` with it. See
+    /// `EncryptionManager::start_decryption_session`.
+    pub async fn start_decryption_session(
+        &self,
+        encryption_mgr: &EncryptionManager,
+        key_id: &str,
+        encrypted_data: &str,
+    ) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+        let doc = self.storage.find(KEY_SHARE_REGISTRY_COLLECTION, key_id).await?
+            .ok_or_else(|| format!("no key-share registry entry for key {}", key_id))?;
+
+        let k = doc.get("k").and_then(|v| v.as_u64())
+            .ok_or("key-share registry entry is missing k")? as u8;
+        let holders: Vec<String> = doc.get("holders").cloned()
+            .map(serde_json::from_value)
+            .transpose()?
+            .ok_or("key-share registry entry is missing holders")?;
+
+        encryption_mgr.start_decryption_session(key_id, k, &holders, self.network.as_ref(), encrypted_data).await
+    }
+
+    /// bootstrap_post_quorum_key generates a fresh group signing key for
+    /// promoting staged entries to KNIRVGRAPH transactions (see
+    /// `crate::post_quorum` and `DistributedCollection::propose_post`),
+    /// hands every currently known peer one threshold secret-sharing share
+    /// of it via `distribute_master_key_shares`, and records its id and
+    /// commitment hash in `POST_QUORUM_REGISTRY_COLLECTION` so a later
+    /// quorum can verify it reconstructed the right key before signing with
+    /// it. This node doesn't keep the private key around once it's been
+    /// split and shipped out. Returns the key id to set as a network's
+    /// `NetworkConfig::post_quorum_key_id`.
+    ///
+    /// There's no real asymmetric PQC scheme backing this (see
+    /// `crypto::pqc`), so the "signature" a completed quorum produces (see
+    /// `post_quorum::try_finalize`) is an HMAC keyed on the reconstructed
+    /// shared secret, not a public-key-verifiable group signature — only a
+    /// node that itself holds (or can reconstruct) the key can check it.
+    /// There is deliberately no `public_key` recorded here: nothing can
+    /// verify against one until this is upgraded to a real threshold
+    /// signature scheme (e.g. FROST/BLS).
+    pub async fn bootstrap_post_quorum_key(&self, threshold: u8) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        let key_pair = PQCKeyPair::generate("post-quorum".to_string(), "post-quorum-signature".to_string())?;
+        self.distribute_master_key_shares(&key_pair, threshold).await?;
+
+        let mut doc = HashMap::new();
+        doc.insert("id".to_string(), serde_json::Value::String(key_pair.id.clone()));
+        doc.insert("commitment".to_string(), serde_json::Value::String(crate::crypto::pqc::commitment_hash(&key_pair.private_key)));
+        self.storage.insert(POST_QUORUM_REGISTRY_COLLECTION, doc).await?;
+
+        Ok(key_pair.id)
+    }
 }
\ No newline at end of file
@@ -0,0 +1,210 @@
+// Basalt-style Byzantine-resistant peer sampling. Each node keeps a
+// fixed-size view of `k` slots; slot `i` is owned by a random salt `s_i`,
+// and the peer held in that slot is always whichever known candidate
+// minimizes `hash(s_i, candidate)` among candidates from distinct IP
+// prefixes (so one host can't win a slot by presenting many addresses of
+// itself). Slots are periodically re-salted to evict stale or adversarial
+// entries, which re-converges the view toward a uniform sample of the
+// network even if a past gossip partner fed it a biased set of candidates.
+
+use std::collections::HashMap;
+use chrono::{DateTime, Utc};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::types::PeerInfo;
+
+/// Default number of slots in a view when `DiscoveryConfig.view_size` is 0.
+pub const DEFAULT_VIEW_SIZE: usize = 20;
+/// Default gossip cadence when `DiscoveryConfig.exchange_interval_secs` is 0.
+pub const DEFAULT_EXCHANGE_INTERVAL_SECS: u64 = 30;
+/// Default re-salting cadence when `DiscoveryConfig.reset_interval_secs` is 0.
+pub const DEFAULT_RESET_INTERVAL_SECS: u64 = 300;
+/// Default number of slots re-salted per reset when `DiscoveryConfig.reset_count` is 0.
+pub const DEFAULT_RESET_COUNT: usize = 2;
+/// Default capacity of a `NodeTable` when none is specified.
+pub const DEFAULT_NODE_TABLE_CAPACITY: usize = 1000;
+/// Storage collection a `NodeTable` is persisted under.
+pub const NODE_TABLE_COLLECTION: &str = "_node_table";
+
+/// prefix groups a peer by the network portion of its primary address (the
+/// first two octets of a dotted-quad, or the bare host otherwise), which is
+/// enough to stop one host from contesting a slot under several addresses.
+fn prefix(peer: &PeerInfo) -> String {
+    let addr = peer.addrs.get(0).map(|s| s.as_str()).unwrap_or("");
+    let host = addr.split(':').next().unwrap_or(addr);
+    let octets: Vec<&str> = host.split('.').collect();
+    if octets.len() == 4 {
+        format!("{}.{}", octets[0], octets[1])
+    } else {
+        host.to_string()
+    }
+}
+
+fn score(salt: u64, peer: &PeerInfo) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(salt.to_be_bytes());
+    hasher.update(peer.peer_id.as_bytes());
+    hasher.finalize().into()
+}
+
+struct Slot {
+    salt: u64,
+    peer: Option<PeerInfo>,
+}
+
+/// BasaltView is one node's self-stabilizing, fixed-size membership sample.
+pub struct BasaltView {
+    slots: Vec<Slot>,
+}
+
+impl BasaltView {
+    /// new creates a view of `k` slots, each with a freshly drawn salt.
+    pub fn new(k: usize) -> Self {
+        let k = if k == 0 { DEFAULT_VIEW_SIZE } else { k };
+        let mut slots = Vec::with_capacity(k);
+        for _ in 0..k {
+            slots.push(Slot { salt: OsRng.next_u64(), peer: None });
+        }
+        BasaltView { slots }
+    }
+
+    /// merge folds `candidates` (typically this view's own members plus
+    /// whatever a gossip partner just sent) back in, re-selecting each
+    /// slot's minimizer. Only the best-scoring candidate per IP-prefix
+    /// group competes for any given slot.
+    pub fn merge(&mut self, candidates: &[PeerInfo]) {
+        for slot in &mut self.slots {
+            // The per-prefix representative depends on this slot's salt
+            // (different slots can prefer different hosts from the same
+            // prefix group), so it's picked fresh per slot rather than once
+            // for the whole view.
+            let mut by_prefix: HashMap<String, (&PeerInfo, [u8; 32])> = HashMap::new();
+            for candidate in candidates {
+                let candidate_score = score(slot.salt, candidate);
+                by_prefix.entry(prefix(candidate))
+                    .and_modify(|(best, best_score)| if candidate_score < *best_score {
+                        *best = candidate;
+                        *best_score = candidate_score;
+                    })
+                    .or_insert((candidate, candidate_score));
+            }
+
+            let mut best = slot.peer.clone();
+            let mut best_score = best.as_ref().map(|p| score(slot.salt, p));
+
+            for (candidate, candidate_score) in by_prefix.values() {
+                if best_score.as_ref().map_or(true, |b| candidate_score < b) {
+                    best = Some((*candidate).clone());
+                    best_score = Some(*candidate_score);
+                }
+            }
+
+            slot.peer = best;
+        }
+    }
+
+    /// reset_salts re-randomizes up to `count` distinct slots, evicting
+    /// whatever peer they held so the next `merge` re-converges them from
+    /// fresh candidates rather than whatever won the slot long ago.
+    pub fn reset_salts(&mut self, count: usize) {
+        let len = self.slots.len();
+        if len == 0 {
+            return;
+        }
+
+        let mut reset = 0;
+        let mut attempts = 0;
+        let mut seen = vec![false; len];
+        while reset < count.min(len) && attempts < len * 4 {
+            let idx = (OsRng.next_u32() as usize) % len;
+            attempts += 1;
+            if seen[idx] {
+                continue;
+            }
+            seen[idx] = true;
+            self.slots[idx].salt = OsRng.next_u64();
+            self.slots[idx].peer = None;
+            reset += 1;
+        }
+    }
+
+    /// sample returns up to `n` currently-filled slots.
+    pub fn sample(&self, n: usize) -> Vec<PeerInfo> {
+        self.slots.iter().filter_map(|s| s.peer.clone()).take(n).collect()
+    }
+
+    /// all returns every currently-filled slot, e.g. to gossip the whole
+    /// view to an exchange partner.
+    pub fn all(&self) -> Vec<PeerInfo> {
+        self.slots.iter().filter_map(|s| s.peer.clone()).collect()
+    }
+}
+
+/// NodeEntry is one known, persisted address for a peer, learned either from
+/// a direct handshake or from a `GetAddr`/`Addr` exchange with a third node.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NodeEntry {
+    pub peer_id: String,
+    pub addrs: Vec<String>,
+    pub last_seen: DateTime<Utc>,
+}
+
+/// NodeTable is a persistent, capacity-bounded directory of known peer
+/// addresses, distinct from a `BasaltView`: where a view is a self-stabilizing
+/// random sample used for gossip fan-out, the table is a plain address book
+/// that lets a node bootstrap from a single contact (via `GetAddr`/`Addr`)
+/// and survive a restart by reloading from the `storage` layer. Entries dedup
+/// by peer id, keeping whichever is freshest, and the least-recently-seen
+/// entries are evicted once `capacity` is exceeded.
+pub struct NodeTable {
+    capacity: usize,
+    entries: HashMap<String, NodeEntry>,
+}
+
+impl NodeTable {
+    /// new creates an empty table bounded to `capacity` entries.
+    pub fn new(capacity: usize) -> Self {
+        let capacity = if capacity == 0 { DEFAULT_NODE_TABLE_CAPACITY } else { capacity };
+        NodeTable { capacity, entries: HashMap::new() }
+    }
+
+    /// insert_many merges `entries` in, keeping the freshest `last_seen` for
+    /// any peer id already present, then evicts least-recently-seen entries
+    /// past `capacity`.
+    pub fn insert_many(&mut self, entries: Vec<NodeEntry>) {
+        for entry in entries {
+            match self.entries.get(&entry.peer_id) {
+                Some(existing) if existing.last_seen >= entry.last_seen => continue,
+                _ => {
+                    self.entries.insert(entry.peer_id.clone(), entry);
+                }
+            }
+        }
+
+        if self.entries.len() > self.capacity {
+            let mut by_age: Vec<(String, DateTime<Utc>)> = self.entries.iter().map(|(id, e)| (id.clone(), e.last_seen)).collect();
+            by_age.sort_by_key(|(_, last_seen)| *last_seen);
+            let excess = self.entries.len() - self.capacity;
+            for (id, _) in by_age.into_iter().take(excess) {
+                self.entries.remove(&id);
+            }
+        }
+    }
+
+    /// sample returns up to `n` entries sorted by `last_seen`, freshest first,
+    /// so the freshest peers are offered first to whoever asks.
+    pub fn sample(&self, n: usize) -> Vec<NodeEntry> {
+        let mut entries: Vec<NodeEntry> = self.entries.values().cloned().collect();
+        entries.sort_by(|a, b| b.last_seen.cmp(&a.last_seen));
+        entries.truncate(n);
+        entries
+    }
+
+    /// all returns every entry, freshest `last_seen` first.
+    pub fn all(&self) -> Vec<NodeEntry> {
+        self.sample(self.entries.len())
+    }
+}
@@ -4,22 +4,33 @@ use tokio::sync::RwLock;
 
 pub mod clock;
 pub mod types;
+pub mod time;
 pub mod crypto;
+pub mod codec;
+pub mod wire;
 pub mod storage;
+pub mod blockstore;
+pub mod discovery;
+pub mod pairing;
 pub mod network;
 pub mod resolver;
 pub mod collection;
+pub mod replication;
 pub mod database;
 pub mod query;
+pub mod post_quorum;
 
 // Re-export commonly used types
 pub use clock::VectorClock;
+pub use codec::{Codec, CodecKind};
 pub use types::*;
+pub use time::*;
 pub use crypto::pqc::*;
 pub use storage::*;
 pub use network::*;
 pub use resolver::*;
 pub use collection::*;
+pub use replication::*;
 pub use database::*;
 pub use query::*;
 
@@ -30,6 +41,12 @@ pub struct Options {
     pub distributed_enabled: bool,
     pub distributed_network_id: String,
     pub distributed_bootstrap_peers: Vec<String>,
+    /// GossipIntervalSecs controls how often the anti-entropy replication
+    /// engine reconciles with peers. Zero uses the package default.
+    pub gossip_interval_secs: u64,
+    /// GossipFanout is how many peers each gossip round reconciles against.
+    /// Zero uses the package default.
+    pub gossip_fanout: usize,
 }
 
 /// DB is the public wrapper around the internal DistributedDatabase
@@ -46,7 +63,7 @@ impl DB {
         }
 
         let storage: Arc<dyn Storage> = Arc::new(FileStorage::new(opts.data_dir)?);
-        let network = Arc::new(NetworkManager::new());
+        let network = Arc::new(NetworkManager::new().with_storage(Arc::clone(&storage)));
 
         let db_opts = DistributedDbOptions {
             distributed: DistributedOptions {
@@ -54,6 +71,8 @@ impl DB {
                 network_id: opts.distributed_network_id,
                 bootstrap_peers: opts.distributed_bootstrap_peers,
             },
+            gossip_interval_secs: opts.gossip_interval_secs,
+            gossip_fanout: opts.gossip_fanout,
         };
 
         let db = DistributedDatabase::new(db_opts, Arc::clone(&storage), network).await?;
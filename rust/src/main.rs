@@ -20,6 +20,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         distributed_enabled: true,
         distributed_network_id: "".to_string(),
         distributed_bootstrap_peers: vec![],
+        gossip_interval_secs: 0,
+        gossip_fanout: 0,
     };
 
     let db = DB::new(opts).await?;
@@ -33,6 +35,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         default_posting_network: "".to_string(),
         auto_post_classifications: vec![],
         private_by_default: true,
+        inline_threshold: 0,
+        post_quorum_key_id: "".to_string(),
         encryption: Default::default(),
         replication: Default::default(),
         discovery: Default::default(),
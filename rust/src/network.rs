@@ -1,16 +1,71 @@
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::RwLock;
 use tokio::net::{TcpListener, TcpStream};
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
-use tokio::sync::Mutex;
+use tokio::io::{ReadHalf, WriteHalf};
+use tokio::sync::{mpsc, oneshot, Mutex};
+use tokio_util::codec::{FramedRead, FramedWrite, LengthDelimitedCodec};
 use futures::future::join_all;
+use futures::{SinkExt, StreamExt};
 use serde_json;
+use crate::codec::{self, CodecKind};
 use crate::types::*;
+use crate::wire::{self, MessageReassembler, DEFAULT_MAX_FRAME_SIZE};
+use crate::crypto::boxstream::{BoxStreamDecryptor, BoxStreamEncryptor};
+use crate::crypto::handshake::{run_client_handshake, run_server_handshake, HandshakeOutcome, LongTermIdentity};
+use crate::crypto::signable::Signable;
+use crate::discovery::{
+    BasaltView, NodeEntry, NodeTable, DEFAULT_EXCHANGE_INTERVAL_SECS, DEFAULT_NODE_TABLE_CAPACITY,
+    DEFAULT_RESET_COUNT, DEFAULT_RESET_INTERVAL_SECS, NODE_TABLE_COLLECTION,
+};
+use crate::pairing::{PairingTable, PROTOCOL_VERSION, SUPPORTED_PROTOCOLS};
+use crate::storage::Storage;
+use rand::RngCore;
+
+/// Cap on how many node-table entries are offered in a single `Addr` reply.
+const ADDR_REPLY_LIMIT: usize = 100;
+
+/// Storage collection this node's own threshold-secret-sharing shares (one
+/// per `key_id` this node has been asked to hold) are persisted under. See
+/// `crate::crypto::threshold`.
+pub(crate) const KEY_SHARE_COLLECTION: &str = "_key_shares";
 
 /// MessageHandler receives a ProtocolMessage
 pub type MessageHandler = Box<dyn Fn(ProtocolMessage) + Send + Sync>;
 
+/// Capacity of the per-connection dispatch channel. Handlers run off of this
+/// channel so a slow handler cannot stall the read loop for a connection.
+const DISPATCH_CHANNEL_CAPACITY: usize = 256;
+
+/// Default time to wait for a reply in `NetworkManager::request` before
+/// giving up and cleaning up the in-flight entry.
+const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// PendingRequests tracks in-flight `request()` calls awaiting a reply,
+/// keyed by the correlation id allocated when the request was sent. The peer
+/// id is kept alongside the oneshot so a dropped connection can cancel only
+/// the requests it was responsible for.
+type PendingRequests = Arc<RwLock<HashMap<u64, (String, oneshot::Sender<ProtocolMessage>)>>>;
+
+/// SecureSink bundles the length-delimited write half of a connection with
+/// the box-stream encryptor derived for it by the secret handshake.
+struct SecureSink {
+    sink: FramedWrite<WriteHalf<TcpStream>, LengthDelimitedCodec>,
+    encryptor: BoxStreamEncryptor,
+}
+
+impl SecureSink {
+    async fn send_message(&mut self, msg: &ProtocolMessage) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        for frame in wire::encode_message(msg)? {
+            let sealed = self.encryptor.seal(&frame)?;
+            self.sink.send(sealed.into()).await?;
+        }
+        Ok(())
+    }
+}
+
 /// Network defines the behaviour used by the distributed components
 #[async_trait::async_trait]
 pub trait Network: Send + Sync {
@@ -21,34 +76,90 @@ pub trait Network: Send + Sync {
     async fn add_collection_to_network(&self, network_id: &str, collection_name: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
     async fn remove_collection_from_network(&self, network_id: &str, collection_name: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
     fn get_network_collections(&self, network_id: &str) -> Vec<String>;
+    /// network_id_for_collection returns the network `collection_name` was
+    /// added to with `add_collection_to_network`, or `None` if it isn't
+    /// associated with any network this node manages. Used by
+    /// `ReplicationEngine` to stamp the real `network_id` onto gossip
+    /// traffic instead of leaving it blank (see `is_gated_message_paired`).
+    fn network_id_for_collection(&self, collection_name: &str) -> Option<String>;
     async fn broadcast_message(&self, network_id: &str, msg: ProtocolMessage) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
     async fn send_to_peer(&self, peer_id: &str, network_id: &str, msg: ProtocolMessage) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
     fn on_message(&self, mt: MessageType, handler: MessageHandler);
+    /// request sends `msg` to `peer_id` and awaits a reply carrying the same
+    /// correlation id, failing if none arrives before the configured request
+    /// timeout elapses.
+    async fn request(&self, peer_id: &str, network_id: &str, msg: ProtocolMessage) -> Result<ProtocolMessage, Box<dyn std::error::Error + Send + Sync>>;
+    /// sample_peers returns up to `n` peers from this node's Basalt view of
+    /// `network_id`, or an empty vec if Basalt sampling isn't enabled for
+    /// that network (`NetworkConfig.discovery.basalt_enabled`).
+    async fn sample_peers(&self, network_id: &str, n: usize) -> Vec<PeerInfo>;
     fn get_network_stats(&self, network_id: &str) -> Option<NetworkStats>;
     fn get_networks(&self) -> Vec<NetworkConfig>;
     fn get_peer_id(&self) -> String;
+    /// get_peers returns every peer this node currently knows of, connected
+    /// or not, across all networks.
+    fn get_peers(&self) -> Vec<PeerInfo>;
+    /// identity returns this node's long-term keypair, used to sign
+    /// outgoing `CRDTOperation`s and `ProtocolMessage`s (see
+    /// `crypto::signable::Signable`) so peers can authenticate them against
+    /// this node's self-certifying `peer_id`.
+    fn identity(&self) -> Arc<LongTermIdentity>;
+    /// negotiated_codec returns the most compact `codec::CodecKind` both
+    /// this node and `peer_id` are known to support, falling back to `Json`
+    /// if the peer is unknown or hasn't advertised a `supported_codecs`
+    /// list yet (see `PeerInfo::supported_codecs`, learned from
+    /// `CollectionAnnounce`).
+    fn negotiated_codec(&self, peer_id: &str) -> CodecKind;
     async fn shutdown(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
 }
 
 /// NetworkManager is a simplified P2P implementation
 pub struct NetworkManager {
     peer_id: String,
+    identity: Arc<LongTermIdentity>,
     networks: Arc<RwLock<HashMap<String, NetworkConfig>>>,
     peers: Arc<RwLock<HashMap<String, PeerInfo>>>,
-    connections: Arc<RwLock<HashMap<String, Arc<Mutex<TcpStream>>>>>,
+    connections: Arc<RwLock<HashMap<String, Arc<Mutex<SecureSink>>>>>,
     stats: Arc<RwLock<HashMap<String, NetworkStats>>>,
     handlers: Arc<RwLock<HashMap<MessageType, Vec<MessageHandler>>>>,
     initialized: Arc<RwLock<bool>>,
     listener: Arc<RwLock<Option<TcpListener>>>,
+    max_frame_size: usize,
+    next_request_id: AtomicU64,
+    pending_requests: PendingRequests,
+    request_timeout: Duration,
+    basalt_views: Arc<RwLock<HashMap<String, BasaltView>>>,
+    basalt_handler_registered: std::sync::atomic::AtomicBool,
+    storage: Option<Arc<dyn Storage>>,
+    node_table: Arc<RwLock<NodeTable>>,
+    addr_handler_registered: std::sync::atomic::AtomicBool,
+    share_handler_registered: std::sync::atomic::AtomicBool,
+    collection_announce_handler_registered: std::sync::atomic::AtomicBool,
+    /// pairing tracks which connected peers have completed the per-network
+    /// `PairRequest`/`PairResponse` exchange (see `crate::pairing`), gating
+    /// `MessageType::SyncRequest`/`Operation` traffic in `read_loop` on it.
+    pairing: Arc<RwLock<PairingTable>>,
+    pairing_handler_registered: std::sync::atomic::AtomicBool,
 }
 
 impl NetworkManager {
-    /// NewNetworkManager creates a new network manager
+    /// NewNetworkManager creates a new network manager with a freshly
+    /// generated long-term identity. The peer id used throughout the
+    /// network layer is the hex-encoded ed25519 public key of that
+    /// identity, authenticated on every connection by the secret handshake.
     pub fn new() -> Self {
-        let peer_id = uuid::Uuid::new_v4().to_string();
+        Self::with_max_frame_size(DEFAULT_MAX_FRAME_SIZE)
+    }
+
+    /// with_max_frame_size creates a network manager that rejects any
+    /// length-delimited frame larger than `max_frame_size` bytes.
+    pub fn with_max_frame_size(max_frame_size: usize) -> Self {
+        let identity = LongTermIdentity::generate();
+        let peer_id = identity.peer_id();
 
         NetworkManager {
             peer_id,
+            identity: Arc::new(identity),
             networks: Arc::new(RwLock::new(HashMap::new())),
             peers: Arc::new(RwLock::new(HashMap::new())),
             connections: Arc::new(RwLock::new(HashMap::new())),
@@ -56,7 +167,296 @@ impl NetworkManager {
             handlers: Arc::new(RwLock::new(HashMap::new())),
             initialized: Arc::new(RwLock::new(false)),
             listener: Arc::new(RwLock::new(None)),
+            max_frame_size,
+            next_request_id: AtomicU64::new(1),
+            pending_requests: Arc::new(RwLock::new(HashMap::new())),
+            request_timeout: DEFAULT_REQUEST_TIMEOUT,
+            basalt_views: Arc::new(RwLock::new(HashMap::new())),
+            basalt_handler_registered: std::sync::atomic::AtomicBool::new(false),
+            storage: None,
+            node_table: Arc::new(RwLock::new(NodeTable::new(DEFAULT_NODE_TABLE_CAPACITY))),
+            addr_handler_registered: std::sync::atomic::AtomicBool::new(false),
+            share_handler_registered: std::sync::atomic::AtomicBool::new(false),
+            collection_announce_handler_registered: std::sync::atomic::AtomicBool::new(false),
+            pairing: Arc::new(RwLock::new(PairingTable::new())),
+            pairing_handler_registered: std::sync::atomic::AtomicBool::new(false),
+        }
+    }
+
+    /// with_request_timeout overrides the default timeout `request` waits
+    /// for a reply before giving up.
+    pub fn with_request_timeout(mut self, timeout: Duration) -> Self {
+        self.request_timeout = timeout;
+        self
+    }
+
+    /// with_storage attaches a storage backend used to persist the node
+    /// table (the `GetAddr`/`Addr`-learned address book) across restarts.
+    /// Without it the table is kept in memory only for the process lifetime.
+    pub fn with_storage(mut self, storage: Arc<dyn Storage>) -> Self {
+        self.storage = Some(storage);
+        self
+    }
+
+    /// ensure_basalt_service starts Basalt peer sampling for `network_id` if
+    /// its config has `discovery.basalt_enabled` set and the service isn't
+    /// already running for it. Safe to call repeatedly (e.g. from both
+    /// `create_network` and `join_network`).
+    async fn ensure_basalt_service(&self, network_id: &str) {
+        let cfg = match self.networks.read().await.get(network_id).cloned() {
+            Some(cfg) => cfg,
+            None => return,
+        };
+
+        if !cfg.discovery.basalt_enabled {
+            return;
+        }
+
+        let inserted = {
+            let mut views = self.basalt_views.write().await;
+            if views.contains_key(network_id) {
+                false
+            } else {
+                views.insert(network_id.to_string(), BasaltView::new(cfg.discovery.view_size));
+                true
+            }
+        };
+
+        if !inserted {
+            return; // service already running for this network
+        }
+
+        self.register_peer_exchange_handler();
+
+        let exchange_interval = Duration::from_secs(if cfg.discovery.exchange_interval_secs == 0 { DEFAULT_EXCHANGE_INTERVAL_SECS } else { cfg.discovery.exchange_interval_secs });
+        let reset_interval = Duration::from_secs(if cfg.discovery.reset_interval_secs == 0 { DEFAULT_RESET_INTERVAL_SECS } else { cfg.discovery.reset_interval_secs });
+        let reset_count = if cfg.discovery.reset_count == 0 { DEFAULT_RESET_COUNT } else { cfg.discovery.reset_count };
+
+        let network_id = network_id.to_string();
+        let peer_id = self.peer_id.clone();
+        let basalt_views = Arc::clone(&self.basalt_views);
+        let connections = Arc::clone(&self.connections);
+        let identity = Arc::clone(&self.identity);
+
+        tokio::spawn(async move {
+            let mut exchange_ticker = tokio::time::interval(exchange_interval);
+            let mut reset_ticker = tokio::time::interval(reset_interval);
+
+            loop {
+                tokio::select! {
+                    _ = exchange_ticker.tick() => {
+                        let members = match basalt_views.read().await.get(&network_id) {
+                            Some(view) => view.all(),
+                            None => continue,
+                        };
+                        if members.is_empty() {
+                            continue;
+                        }
+
+                        let target = &members[(rand::rngs::OsRng.next_u32() as usize) % members.len()];
+                        if target.peer_id == peer_id {
+                            continue;
+                        }
+
+                        let payload = match serde_json::to_value(&members) {
+                            Ok(v) => v,
+                            Err(_) => continue,
+                        };
+                        let (payload, content_codec) = codec::json_payload(payload);
+                        let mut msg = ProtocolMessage {
+                            msg_type: MessageType::PeerExchange,
+                            network_id: network_id.clone(),
+                            sender_id: peer_id.clone(),
+                            timestamp: chrono::Utc::now().timestamp(),
+                            payload,
+                            content_codec,
+                            request_id: None,
+                            signature: None,
+                        };
+                        msg.sign(&identity);
+
+                        let sink = connections.read().await.get(&target.peer_id).cloned();
+                        if let Some(sink) = sink {
+                            let _ = sink.lock().await.send_message(&msg).await;
+                        }
+                    }
+                    _ = reset_ticker.tick() => {
+                        if let Some(view) = basalt_views.write().await.get_mut(&network_id) {
+                            view.reset_salts(reset_count);
+                        }
+                    }
+                }
+            }
+        });
+    }
+
+    /// register_peer_exchange_handler wires up the one, process-wide handler
+    /// that merges an incoming Basalt view into the matching network's view
+    /// and dials any newly discovered peer. Idempotent: only the first call
+    /// actually registers anything.
+    fn register_peer_exchange_handler(&self) {
+        if self.basalt_handler_registered.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        let our_peer_id = self.peer_id.clone();
+        let basalt_views = Arc::clone(&self.basalt_views);
+        let identity = Arc::clone(&self.identity);
+        let networks = Arc::clone(&self.networks);
+        let peers = Arc::clone(&self.peers);
+        let connections = Arc::clone(&self.connections);
+        let stats = Arc::clone(&self.stats);
+        let handlers = Arc::clone(&self.handlers);
+        let pending_requests = Arc::clone(&self.pending_requests);
+        let pairing = Arc::clone(&self.pairing);
+        let max_frame_size = self.max_frame_size;
+
+        self.on_message(MessageType::PeerExchange, Box::new(move |msg: ProtocolMessage| {
+            tokio::spawn(handle_peer_exchange(
+                msg,
+                our_peer_id.clone(),
+                Arc::clone(&basalt_views),
+                Arc::clone(&identity),
+                Arc::clone(&networks),
+                Arc::clone(&peers),
+                Arc::clone(&connections),
+                Arc::clone(&stats),
+                Arc::clone(&handlers),
+                Arc::clone(&pending_requests),
+                Arc::clone(&pairing),
+                max_frame_size,
+            ));
+        }));
+    }
+
+    /// register_addr_handlers wires up the process-wide handlers that answer
+    /// an inbound `GetAddr` with a sample of this node's known address table
+    /// and merge an inbound `Addr` reply into it, persisting newly learned
+    /// entries to `storage` (if configured) and dialing any peer discovered
+    /// that this node isn't already connected to. This is what lets
+    /// `join_network` bootstrap from a single peer and learn the rest of the
+    /// network. Idempotent: only the first call actually registers anything.
+    fn register_addr_handlers(&self) {
+        if self.addr_handler_registered.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        let our_peer_id = self.peer_id.clone();
+        let node_table = Arc::clone(&self.node_table);
+        let connections = Arc::clone(&self.connections);
+        let identity = Arc::clone(&self.identity);
+
+        self.on_message(MessageType::GetAddr, Box::new(move |msg: ProtocolMessage| {
+            tokio::spawn(handle_get_addr(msg, our_peer_id.clone(), Arc::clone(&node_table), Arc::clone(&connections), Arc::clone(&identity)));
+        }));
+
+        let our_peer_id = self.peer_id.clone();
+        let node_table = Arc::clone(&self.node_table);
+        let storage = self.storage.clone();
+        let identity = Arc::clone(&self.identity);
+        let networks = Arc::clone(&self.networks);
+        let peers = Arc::clone(&self.peers);
+        let connections = Arc::clone(&self.connections);
+        let stats = Arc::clone(&self.stats);
+        let handlers = Arc::clone(&self.handlers);
+        let pending_requests = Arc::clone(&self.pending_requests);
+        let pairing = Arc::clone(&self.pairing);
+        let max_frame_size = self.max_frame_size;
+
+        self.on_message(MessageType::Addr, Box::new(move |msg: ProtocolMessage| {
+            tokio::spawn(handle_addr(
+                msg,
+                our_peer_id.clone(),
+                Arc::clone(&node_table),
+                storage.clone(),
+                Arc::clone(&identity),
+                Arc::clone(&networks),
+                Arc::clone(&peers),
+                Arc::clone(&connections),
+                Arc::clone(&stats),
+                Arc::clone(&handlers),
+                Arc::clone(&pending_requests),
+                Arc::clone(&pairing),
+                max_frame_size,
+            ));
+        }));
+    }
+
+    /// register_share_handlers wires up the process-wide handlers that store
+    /// an inbound `ShareDistribute` (this node being handed a threshold
+    /// secret-sharing share to hold) and answer an inbound `ShareRequest`
+    /// with whatever share this node holds for the requested `key_id`, as
+    /// part of a `start_decryption_session`. See `crate::crypto::threshold`.
+    /// Idempotent: only the first call actually registers anything.
+    fn register_share_handlers(&self) {
+        if self.share_handler_registered.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        let storage = self.storage.clone();
+        self.on_message(MessageType::ShareDistribute, Box::new(move |msg: ProtocolMessage| {
+            tokio::spawn(handle_share_distribute(msg, storage.clone()));
+        }));
+
+        let storage = self.storage.clone();
+        let our_peer_id = self.peer_id.clone();
+        let connections = Arc::clone(&self.connections);
+        let identity = Arc::clone(&self.identity);
+        self.on_message(MessageType::ShareRequest, Box::new(move |msg: ProtocolMessage| {
+            tokio::spawn(handle_share_request(msg, our_peer_id.clone(), storage.clone(), Arc::clone(&connections), Arc::clone(&identity)));
+        }));
+    }
+
+    /// register_collection_announce_handler wires up the process-wide
+    /// handler that records a `CollectionAnnounce` sender's advertised
+    /// `supported_codecs` against its `PeerInfo` entry, so later messages to
+    /// that peer can negotiate a more compact codec than the `Json`
+    /// fallback (see `codec::CodecKind::negotiate`). Idempotent: only the
+    /// first call actually registers anything.
+    fn register_collection_announce_handler(&self) {
+        if self.collection_announce_handler_registered.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        let peers = Arc::clone(&self.peers);
+        self.on_message(MessageType::CollectionAnnounce, Box::new(move |msg: ProtocolMessage| {
+            tokio::spawn(handle_collection_announce(msg, Arc::clone(&peers)));
+        }));
+    }
+
+    /// register_pairing_handlers wires up the process-wide handlers that
+    /// answer an inbound `PairRequest` with this node's own `NodeInfo` and
+    /// complete pairing on the dialing side once the matching
+    /// `PairResponse` arrives. See `crate::pairing`. Idempotent: only the
+    /// first call actually registers anything.
+    fn register_pairing_handlers(&self) {
+        if self.pairing_handler_registered.swap(true, Ordering::SeqCst) {
+            return;
         }
+
+        let our_peer_id = self.peer_id.clone();
+        let identity = Arc::clone(&self.identity);
+        let networks = Arc::clone(&self.networks);
+        let peers = Arc::clone(&self.peers);
+        let pairing = Arc::clone(&self.pairing);
+        let connections = Arc::clone(&self.connections);
+        self.on_message(MessageType::PairRequest, Box::new(move |msg: ProtocolMessage| {
+            tokio::spawn(handle_pair_request(
+                msg,
+                our_peer_id.clone(),
+                Arc::clone(&identity),
+                Arc::clone(&networks),
+                Arc::clone(&peers),
+                Arc::clone(&pairing),
+                Arc::clone(&connections),
+            ));
+        }));
+
+        let peers = Arc::clone(&self.peers);
+        let pairing = Arc::clone(&self.pairing);
+        self.on_message(MessageType::PairResponse, Box::new(move |msg: ProtocolMessage| {
+            tokio::spawn(handle_pair_response(msg, Arc::clone(&peers), Arc::clone(&pairing)));
+        }));
     }
 }
 
@@ -76,29 +476,75 @@ impl Network for NetworkManager {
 
         println!("Network manager initialized: {} on {:?}", self.peer_id, self.listener.read().await.as_ref().unwrap().local_addr()?);
 
-        // Start accepting connections
-        // TODO: Implement connection accepting loop
-        // let networks = Arc::clone(&self.networks);
-        // let peers = Arc::clone(&self.peers);
-        // let connections = Arc::clone(&self.connections);
-        // let handlers = Arc::clone(&self.handlers);
-        // let peer_id = self.peer_id.clone();
-
-        // tokio::spawn(async move {
-        //     while let Ok((stream, _)) = listener.accept().await {
-        //         let networks = Arc::clone(&networks);
-        //         let peers = Arc::clone(&peers);
-        //         let connections = Arc::clone(&self.connections);
-        //         let handlers = Arc::clone(&handlers);
-        //         let peer_id = peer_id.clone();
-
-        //         tokio::spawn(async move {
-        //             if let Err(e) = handle_connection(stream, networks, peers, connections, handlers, peer_id).await {
-        //                 eprintln!("Connection error: {}", e);
-        //             }
-        //         });
-        //     }
-        // });
+        // Reload the node table persisted by a previous run, if a storage
+        // backend was attached via `with_storage`, so a restart doesn't lose
+        // every address this node ever learned.
+        if let Some(storage) = &self.storage {
+            if let Ok(docs) = storage.find_all(NODE_TABLE_COLLECTION).await {
+                let entries: Vec<NodeEntry> = docs
+                    .into_iter()
+                    .filter_map(|doc| serde_json::from_value(serde_json::Value::Object(doc.into_iter().collect())).ok())
+                    .collect();
+                if !entries.is_empty() {
+                    self.node_table.write().await.insert_many(entries);
+                }
+            }
+        }
+        self.register_addr_handlers();
+        self.register_share_handlers();
+        self.register_collection_announce_handler();
+        self.register_pairing_handlers();
+
+        // Start accepting connections. Each inbound stream gets its own
+        // read/write-split handler task so a slow peer only blocks its own
+        // connection, never the accept loop itself.
+        let stats = Arc::clone(&self.stats);
+        let peers = Arc::clone(&self.peers);
+        let connections = Arc::clone(&self.connections);
+        let handlers = Arc::clone(&self.handlers);
+        let listener = Arc::clone(&self.listener);
+        let networks = Arc::clone(&self.networks);
+        let identity = Arc::clone(&self.identity);
+        let pending_requests = Arc::clone(&self.pending_requests);
+        let pairing = Arc::clone(&self.pairing);
+        let max_frame_size = self.max_frame_size;
+
+        tokio::spawn(async move {
+            loop {
+                // The listener lives behind the RwLock for the duration of the
+                // server, so briefly re-borrow it for each accept() call.
+                let accepted = {
+                    let listener = listener.read().await;
+                    match listener.as_ref() {
+                        Some(listener) => listener.accept().await,
+                        None => break,
+                    }
+                };
+
+                let (stream, _addr) = match accepted {
+                    Ok(pair) => pair,
+                    Err(e) => {
+                        eprintln!("accept error: {}", e);
+                        continue;
+                    }
+                };
+
+                let peers = Arc::clone(&peers);
+                let connections = Arc::clone(&connections);
+                let stats = Arc::clone(&stats);
+                let handlers = Arc::clone(&handlers);
+                let networks = Arc::clone(&networks);
+                let identity = Arc::clone(&identity);
+                let pending_requests = Arc::clone(&pending_requests);
+                let pairing = Arc::clone(&pairing);
+
+                tokio::spawn(async move {
+                    if let Err(e) = handle_connection(stream, peers, connections, stats, handlers, networks, identity, pending_requests, pairing, max_frame_size).await {
+                        eprintln!("connection error: {}", e);
+                    }
+                });
+            }
+        });
 
         Ok(())
     }
@@ -113,6 +559,7 @@ impl Network for NetworkManager {
 
         cfg.collections = HashMap::new();
         networks.insert(cfg.network_id.clone(), cfg.clone());
+        drop(networks);
 
         let mut stats = self.stats.write().await;
         stats.insert(cfg.network_id.clone(), NetworkStats {
@@ -125,8 +572,10 @@ impl Network for NetworkManager {
             bytes_transferred: 0,
             average_latency: chrono::Duration::zero(),
         });
+        drop(stats);
 
         println!("Created network {}", cfg.network_id);
+        self.ensure_basalt_service(&cfg.network_id).await;
         Ok(cfg.network_id)
     }
 
@@ -143,6 +592,8 @@ impl Network for NetworkManager {
                 default_posting_network: "".to_string(),
                 auto_post_classifications: vec![],
                 private_by_default: true,
+                inline_threshold: 0,
+                post_quorum_key_id: "".to_string(),
                 encryption: Default::default(),
                 replication: Default::default(),
                 discovery: Default::default(),
@@ -160,43 +611,27 @@ impl Network for NetworkManager {
                 average_latency: chrono::Duration::zero(),
             });
         }
+        drop(networks);
 
-        // Connect to bootstrap peers (simplified)
+        // Connect to bootstrap peers, authenticating each with the secret handshake.
         for peer_addr in bootstrap_peers {
-            let connections = Arc::clone(&self.connections);
-            let peers = Arc::clone(&self.peers);
-            let peer_id = self.peer_id.clone();
-
-            tokio::spawn(async move {
-                if let Ok(stream) = TcpStream::connect(&peer_addr).await {
-                    // Simple handshake
-                    let mut stream = stream;
-                    let handshake = format!("KNIRV:{}\n", peer_id);
-                    if stream.write_all(handshake.as_bytes()).await.is_ok() {
-                        let mut reader = BufReader::new(&mut stream);
-                        let mut response = String::new();
-                        if reader.read_line(&mut response).await.is_ok() {
-                            let response = response.trim();
-                            if response.starts_with("KNIRV:") {
-                                let remote_peer_id = &response[6..];
-                                let mut connections = connections.write().await;
-                                let mut peers = peers.write().await;
-                                connections.insert(remote_peer_id.to_string(), Arc::new(Mutex::new(stream)));
-                                peers.insert(remote_peer_id.to_string(), PeerInfo {
-                                    peer_id: remote_peer_id.to_string(),
-                                    addrs: vec![peer_addr],
-                                    protocols: vec![],
-                                    latency: chrono::Duration::zero(),
-                                    last_seen: chrono::Utc::now(),
-                                    collections: vec![],
-                                });
-                            }
-                        }
-                    }
-                }
-            });
+            tokio::spawn(dial_and_register(
+                peer_addr,
+                network_id.to_string(),
+                Arc::clone(&self.identity),
+                Arc::clone(&self.networks),
+                Arc::clone(&self.peers),
+                Arc::clone(&self.connections),
+                Arc::clone(&self.stats),
+                Arc::clone(&self.handlers),
+                Arc::clone(&self.pending_requests),
+                Arc::clone(&self.pairing),
+                self.max_frame_size,
+            ));
         }
 
+        self.ensure_basalt_service(network_id).await;
+
         Ok(())
     }
 
@@ -222,13 +657,24 @@ impl Network for NetworkManager {
             }
         }
 
-        // Broadcast collection announcement (simplified)
+        // Broadcast collection announcement (simplified). This also doubles
+        // as this node's codec advertisement: a recipient's
+        // `handle_collection_announce` records `supported_codecs` against
+        // our `PeerInfo` entry so later messages to us can negotiate a more
+        // compact format than the `Json` fallback.
+        let (payload, content_codec) = codec::json_payload(serde_json::json!({
+            "collection": collection_name,
+            "supported_codecs": CodecKind::all(),
+        }));
         let msg = ProtocolMessage {
             msg_type: MessageType::CollectionAnnounce,
             network_id: network_id.to_string(),
             sender_id: self.peer_id.clone(),
             timestamp: chrono::Utc::now().timestamp(),
-            payload: serde_json::json!({ "collection": collection_name }),
+            payload,
+            content_codec,
+            request_id: None,
+            signature: None,
         };
 
         self.broadcast_message(network_id, msg).await
@@ -256,21 +702,29 @@ impl Network for NetworkManager {
         }
     }
 
-    async fn broadcast_message(&self, network_id: &str, msg: ProtocolMessage) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    fn network_id_for_collection(&self, collection_name: &str) -> Option<String> {
+        let networks = self.networks.try_read().ok()?;
+        networks.values()
+            .find(|network| network.collections.contains_key(collection_name))
+            .map(|network| network.network_id.clone())
+    }
+
+    async fn broadcast_message(&self, network_id: &str, mut msg: ProtocolMessage) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         if !*self.initialized.read().await {
             return Err("not initialized".into());
         }
 
-        let data = serde_json::to_string(&msg)?;
+        msg.sign(&self.identity);
+
         let connections = self.connections.read().await;
         let mut tasks = vec![];
 
-        for stream in connections.values() {
-            let data = data.clone();
-            let stream = Arc::clone(stream);
+        for sink in connections.values() {
+            let msg = msg.clone();
+            let sink = Arc::clone(sink);
             tasks.push(async move {
-                let mut stream = stream.lock().await;
-                let _ = stream.write_all(format!("{}\n", data).as_bytes()).await;
+                let mut sink = sink.lock().await;
+                let _ = sink.send_message(&msg).await;
             });
         }
 
@@ -278,17 +732,18 @@ impl Network for NetworkManager {
         Ok(())
     }
 
-    async fn send_to_peer(&self, peer_id: &str, _network_id: &str, msg: ProtocolMessage) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    async fn send_to_peer(&self, peer_id: &str, _network_id: &str, mut msg: ProtocolMessage) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         if !*self.initialized.read().await {
             return Err("not initialized".into());
         }
 
-        let data = serde_json::to_string(&msg)?;
+        msg.sign(&self.identity);
+
         let connections = self.connections.read().await;
 
-        if let Some(stream) = connections.get(peer_id) {
-            let mut stream = stream.lock().await;
-            stream.write_all(format!("{}\n", data).as_bytes()).await?;
+        if let Some(sink) = connections.get(peer_id) {
+            let mut sink = sink.lock().await;
+            sink.send_message(&msg).await?;
         } else {
             return Err("peer not connected".into());
         }
@@ -301,6 +756,49 @@ impl Network for NetworkManager {
         handlers.entry(mt).or_insert_with(Vec::new).push(handler);
     }
 
+    async fn request(&self, peer_id: &str, network_id: &str, mut msg: ProtocolMessage) -> Result<ProtocolMessage, Box<dyn std::error::Error + Send + Sync>> {
+        if !*self.initialized.read().await {
+            return Err("not initialized".into());
+        }
+
+        let request_id = self.next_request_id.fetch_add(1, Ordering::SeqCst);
+        msg.network_id = network_id.to_string();
+        msg.request_id = Some(request_id);
+        msg.sign(&self.identity);
+
+        let (tx, rx) = oneshot::channel();
+        self.pending_requests.write().await.insert(request_id, (peer_id.to_string(), tx));
+
+        let sink = {
+            let connections = self.connections.read().await;
+            match connections.get(peer_id) {
+                Some(sink) => Arc::clone(sink),
+                None => {
+                    self.pending_requests.write().await.remove(&request_id);
+                    return Err("peer not connected".into());
+                }
+            }
+        };
+
+        if let Err(e) = sink.lock().await.send_message(&msg).await {
+            self.pending_requests.write().await.remove(&request_id);
+            return Err(e);
+        }
+
+        match tokio::time::timeout(self.request_timeout, rx).await {
+            Ok(Ok(response)) => Ok(response),
+            Ok(Err(_)) => Err("request cancelled: connection to peer was lost".into()),
+            Err(_) => {
+                self.pending_requests.write().await.remove(&request_id);
+                Err("request timed out waiting for reply".into())
+            }
+        }
+    }
+
+    async fn sample_peers(&self, network_id: &str, n: usize) -> Vec<PeerInfo> {
+        self.basalt_views.read().await.get(network_id).map(|v| v.sample(n)).unwrap_or_default()
+    }
+
     fn get_network_stats(&self, network_id: &str) -> Option<NetworkStats> {
         self.stats.try_read().ok().and_then(|s| s.get(network_id).cloned())
     }
@@ -313,6 +811,21 @@ impl Network for NetworkManager {
         self.peer_id.clone()
     }
 
+    fn get_peers(&self) -> Vec<PeerInfo> {
+        self.peers.try_read().map(|p| p.values().cloned().collect()).unwrap_or_default()
+    }
+
+    fn identity(&self) -> Arc<LongTermIdentity> {
+        Arc::clone(&self.identity)
+    }
+
+    fn negotiated_codec(&self, peer_id: &str) -> CodecKind {
+        let theirs = self.peers.try_read().ok()
+            .and_then(|peers| peers.get(peer_id).map(|info| info.supported_codecs.clone()))
+            .unwrap_or_else(|| vec![CodecKind::Json]);
+        CodecKind::negotiate(&CodecKind::all(), &theirs)
+    }
+
     async fn shutdown(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         *self.initialized.write().await = false;
 
@@ -327,14 +840,706 @@ impl Network for NetworkManager {
     }
 }
 
+/// dial_and_register connects to `peer_addr`, authenticates with the client
+/// side of the secret handshake, records the resulting peer, and promotes
+/// the connection to a box-stream. Shared by the static `bootstrap_peers`
+/// dial in `join_network` and by newly discovered addresses fed in by
+/// Basalt peer exchange.
+async fn dial_and_register(
+    peer_addr: String,
+    network_id: String,
+    identity: Arc<LongTermIdentity>,
+    networks: Arc<RwLock<HashMap<String, NetworkConfig>>>,
+    peers: Arc<RwLock<HashMap<String, PeerInfo>>>,
+    connections: Arc<RwLock<HashMap<String, Arc<Mutex<SecureSink>>>>>,
+    stats: Arc<RwLock<HashMap<String, NetworkStats>>>,
+    handlers: Arc<RwLock<HashMap<MessageType, Vec<MessageHandler>>>>,
+    pending_requests: PendingRequests,
+    pairing: Arc<RwLock<PairingTable>>,
+    max_frame_size: usize,
+) {
+    let mut stream = match TcpStream::connect(&peer_addr).await {
+        Ok(stream) => stream,
+        Err(e) => {
+            eprintln!("failed to connect to {}: {}", peer_addr, e);
+            return;
+        }
+    };
+
+    let outcome = match run_client_handshake(&mut stream, &network_id, &identity).await {
+        Ok(outcome) => outcome,
+        Err(e) => {
+            eprintln!("secret handshake with {} failed: {}", peer_addr, e);
+            return;
+        }
+    };
+
+    {
+        let mut peers = peers.write().await;
+        peers.insert(outcome.remote_peer_id.clone(), PeerInfo {
+            peer_id: outcome.remote_peer_id.clone(),
+            addrs: vec![peer_addr],
+            protocols: vec![],
+            latency: chrono::Duration::zero(),
+            last_seen: chrono::Utc::now(),
+            collections: vec![],
+            supported_codecs: vec![CodecKind::Json],
+        });
+    }
+
+    let our_peer_id = identity.peer_id();
+    register_secure_connection(stream, outcome, network_id, our_peer_id, identity, networks, peers, connections, stats, handlers, pending_requests, pairing, max_frame_size).await;
+}
+
+/// handle_peer_exchange merges an inbound Basalt view into the matching
+/// network's view (if this node runs Basalt for it) and dials any peer it
+/// discovers that it isn't already connected to.
+async fn handle_peer_exchange(
+    msg: ProtocolMessage,
+    our_peer_id: String,
+    basalt_views: Arc<RwLock<HashMap<String, BasaltView>>>,
+    identity: Arc<LongTermIdentity>,
+    networks: Arc<RwLock<HashMap<String, NetworkConfig>>>,
+    peers: Arc<RwLock<HashMap<String, PeerInfo>>>,
+    connections: Arc<RwLock<HashMap<String, Arc<Mutex<SecureSink>>>>>,
+    stats: Arc<RwLock<HashMap<String, NetworkStats>>>,
+    handlers: Arc<RwLock<HashMap<MessageType, Vec<MessageHandler>>>>,
+    pending_requests: PendingRequests,
+    pairing: Arc<RwLock<PairingTable>>,
+    max_frame_size: usize,
+) {
+    let candidates: Vec<PeerInfo> = match msg.content_codec.decode(&msg.payload) {
+        Ok(c) => c,
+        Err(_) => return,
+    };
+
+    let participating = {
+        let mut views = basalt_views.write().await;
+        match views.get_mut(&msg.network_id) {
+            Some(view) => {
+                view.merge(&candidates);
+                true
+            }
+            None => false,
+        }
+    };
+
+    if !participating {
+        return; // this node isn't running Basalt for that network
+    }
+
+    for candidate in candidates {
+        if candidate.peer_id == our_peer_id {
+            continue;
+        }
+
+        let already_connected = connections.read().await.contains_key(&candidate.peer_id);
+        if already_connected {
+            continue;
+        }
+
+        if let Some(addr) = candidate.addrs.first().cloned() {
+            tokio::spawn(dial_and_register(
+                addr,
+                msg.network_id.clone(),
+                Arc::clone(&identity),
+                Arc::clone(&networks),
+                Arc::clone(&peers),
+                Arc::clone(&connections),
+                Arc::clone(&stats),
+                Arc::clone(&handlers),
+                Arc::clone(&pending_requests),
+                Arc::clone(&pairing),
+                max_frame_size,
+            ));
+        }
+    }
+}
+
+/// handle_get_addr answers an inbound `GetAddr` with a sample of this
+/// node's known node table, freshest `last_seen` first, excluding the asker.
+async fn handle_get_addr(
+    msg: ProtocolMessage,
+    our_peer_id: String,
+    node_table: Arc<RwLock<NodeTable>>,
+    connections: Arc<RwLock<HashMap<String, Arc<Mutex<SecureSink>>>>>,
+    identity: Arc<LongTermIdentity>,
+) {
+    let mut entries = node_table.read().await.sample(ADDR_REPLY_LIMIT);
+    entries.retain(|e| e.peer_id != msg.sender_id);
+
+    let payload = match serde_json::to_value(&entries) {
+        Ok(v) => v,
+        Err(_) => return,
+    };
+    let (payload, content_codec) = codec::json_payload(payload);
+
+    let mut reply = ProtocolMessage {
+        msg_type: MessageType::Addr,
+        network_id: msg.network_id,
+        sender_id: our_peer_id,
+        timestamp: chrono::Utc::now().timestamp(),
+        payload,
+        content_codec,
+        request_id: None,
+        signature: None,
+    };
+    reply.sign(&identity);
+
+    if let Some(sink) = connections.read().await.get(&msg.sender_id).cloned() {
+        let _ = sink.lock().await.send_message(&reply).await;
+    }
+}
+
+/// handle_addr merges an inbound `Addr` reply into the node table, persists
+/// newly learned entries to `storage` (if configured), and dials any peer
+/// discovered that this node isn't already connected to — the mechanism
+/// that lets a node bootstrap from a single peer and learn the rest of the
+/// network.
+async fn handle_addr(
+    msg: ProtocolMessage,
+    our_peer_id: String,
+    node_table: Arc<RwLock<NodeTable>>,
+    storage: Option<Arc<dyn Storage>>,
+    identity: Arc<LongTermIdentity>,
+    networks: Arc<RwLock<HashMap<String, NetworkConfig>>>,
+    peers: Arc<RwLock<HashMap<String, PeerInfo>>>,
+    connections: Arc<RwLock<HashMap<String, Arc<Mutex<SecureSink>>>>>,
+    stats: Arc<RwLock<HashMap<String, NetworkStats>>>,
+    handlers: Arc<RwLock<HashMap<MessageType, Vec<MessageHandler>>>>,
+    pending_requests: PendingRequests,
+    pairing: Arc<RwLock<PairingTable>>,
+    max_frame_size: usize,
+) {
+    let mut entries: Vec<NodeEntry> = match msg.content_codec.decode(&msg.payload) {
+        Ok(e) => e,
+        Err(_) => return,
+    };
+    entries.retain(|e| e.peer_id != our_peer_id);
+    if entries.is_empty() {
+        return;
+    }
+
+    node_table.write().await.insert_many(entries.clone());
+
+    if let Some(storage) = &storage {
+        for entry in &entries {
+            let doc = match serde_json::to_value(entry).and_then(serde_json::from_value::<HashMap<String, serde_json::Value>>) {
+                Ok(mut doc) => {
+                    doc.insert("id".to_string(), serde_json::Value::String(entry.peer_id.clone()));
+                    doc
+                }
+                Err(_) => continue,
+            };
+            let _ = storage.insert(NODE_TABLE_COLLECTION, doc).await;
+        }
+    }
+
+    for entry in entries {
+        if connections.read().await.contains_key(&entry.peer_id) {
+            continue;
+        }
+        if let Some(addr) = entry.addrs.first().cloned() {
+            tokio::spawn(dial_and_register(
+                addr,
+                msg.network_id.clone(),
+                Arc::clone(&identity),
+                Arc::clone(&networks),
+                Arc::clone(&peers),
+                Arc::clone(&connections),
+                Arc::clone(&stats),
+                Arc::clone(&handlers),
+                Arc::clone(&pending_requests),
+                Arc::clone(&pairing),
+                max_frame_size,
+            ));
+        }
+    }
+}
+
+/// handle_share_distribute persists an inbound `ShareDistribute` (this
+/// node's threshold-secret-sharing share of some `key_id`) to `storage`
+/// under `KEY_SHARE_COLLECTION`, keyed by `key_id` so a later `ShareRequest`
+/// for the same key can look it up. A node with no storage backend attached
+/// silently drops the share; it can still serve as a relay but never as a
+/// share holder.
+async fn handle_share_distribute(msg: ProtocolMessage, storage: Option<Arc<dyn Storage>>) {
+    let storage = match storage {
+        Some(storage) => storage,
+        None => return,
+    };
+
+    let payload = codec::payload_value(msg.content_codec, &msg.payload);
+    let key_id = match payload.get("key_id").and_then(|v| v.as_str()) {
+        Some(id) => id.to_string(),
+        None => return,
+    };
+    let index = match payload.get("index").and_then(|v| v.as_u64()) {
+        Some(i) => i,
+        None => return,
+    };
+    let data = match payload.get("data") {
+        Some(v) => v.clone(),
+        None => return,
+    };
+
+    let mut doc = HashMap::new();
+    doc.insert("id".to_string(), serde_json::Value::String(key_id));
+    doc.insert("index".to_string(), serde_json::Value::from(index));
+    doc.insert("data".to_string(), data);
+    let _ = storage.insert(KEY_SHARE_COLLECTION, doc).await;
+}
+
+/// handle_share_request answers an inbound `ShareRequest` with the share
+/// this node holds for the requested `key_id`, echoing the request's
+/// correlation id so it completes the requester's `network.request()`
+/// oneshot. Silently does nothing if this node holds no such share or has no
+/// storage backend attached; the requester's decryption session simply
+/// moves on to its next holder.
+async fn handle_share_request(
+    msg: ProtocolMessage,
+    our_peer_id: String,
+    storage: Option<Arc<dyn Storage>>,
+    connections: Arc<RwLock<HashMap<String, Arc<Mutex<SecureSink>>>>>,
+    identity: Arc<LongTermIdentity>,
+) {
+    let storage = match storage {
+        Some(storage) => storage,
+        None => return,
+    };
+    let request_id = match msg.request_id {
+        Some(id) => id,
+        None => return,
+    };
+    let payload = codec::payload_value(msg.content_codec, &msg.payload);
+    let key_id = match payload.get("key_id").and_then(|v| v.as_str()) {
+        Some(id) => id,
+        None => return,
+    };
+
+    let doc = match storage.find(KEY_SHARE_COLLECTION, key_id).await {
+        Ok(Some(doc)) => doc,
+        _ => return,
+    };
+    let (index, data) = match (doc.get("index"), doc.get("data")) {
+        (Some(index), Some(data)) => (index.clone(), data.clone()),
+        _ => return,
+    };
+
+    let (payload, content_codec) = codec::json_payload(serde_json::json!({ "index": index, "data": data }));
+    let mut reply = ProtocolMessage {
+        msg_type: MessageType::ShareResponse,
+        network_id: msg.network_id,
+        sender_id: our_peer_id,
+        timestamp: chrono::Utc::now().timestamp(),
+        payload,
+        content_codec,
+        request_id: Some(request_id),
+        signature: None,
+    };
+    reply.sign(&identity);
+
+    if let Some(sink) = connections.read().await.get(&msg.sender_id).cloned() {
+        let _ = sink.lock().await.send_message(&reply).await;
+    }
+}
+
+/// handle_collection_announce records the sender's advertised
+/// `supported_codecs` against its `PeerInfo` entry, if this node already
+/// has one (i.e. the sender is a direct connection). A sender this node
+/// doesn't otherwise know about is ignored rather than given a synthetic
+/// `PeerInfo` with no address to dial.
+async fn handle_collection_announce(msg: ProtocolMessage, peers: Arc<RwLock<HashMap<String, PeerInfo>>>) {
+    let payload = codec::payload_value(msg.content_codec, &msg.payload);
+    let supported_codecs: Vec<CodecKind> = match payload.get("supported_codecs").cloned().map(serde_json::from_value) {
+        Some(Ok(codecs)) => codecs,
+        _ => return,
+    };
+
+    if let Some(peer) = peers.write().await.get_mut(&msg.sender_id) {
+        peer.supported_codecs = supported_codecs;
+    }
+}
+
+/// handle_pair_request answers an inbound `PairRequest` by recording the
+/// sender's claimed `NodeInfo` against its `PeerInfo` entry, marking it
+/// paired for `msg.network_id`, and replying with this node's own
+/// `NodeInfo` for that same network — completing the requester's side of
+/// pairing once it arrives there (`handle_pair_response`). Ignored if
+/// `msg.network_id` isn't one of this node's networks, the claimed
+/// `NodeInfo.peer_id` doesn't match the connection's authenticated
+/// `sender_id`, or the sender has no `PeerInfo` entry yet (i.e. isn't a
+/// direct connection). See `crate::pairing`.
+async fn handle_pair_request(
+    msg: ProtocolMessage,
+    our_peer_id: String,
+    identity: Arc<LongTermIdentity>,
+    networks: Arc<RwLock<HashMap<String, NetworkConfig>>>,
+    peers: Arc<RwLock<HashMap<String, PeerInfo>>>,
+    pairing: Arc<RwLock<PairingTable>>,
+    connections: Arc<RwLock<HashMap<String, Arc<Mutex<SecureSink>>>>>,
+) {
+    if !networks.read().await.contains_key(&msg.network_id) {
+        return;
+    }
+
+    let payload = codec::payload_value(msg.content_codec, &msg.payload);
+    let node_info: NodeInfo = match serde_json::from_value(payload) {
+        Ok(info) => info,
+        Err(_) => return,
+    };
+    if node_info.peer_id != msg.sender_id {
+        return;
+    }
+
+    {
+        let mut peers = peers.write().await;
+        let peer = match peers.get_mut(&msg.sender_id) {
+            Some(peer) => peer,
+            None => return,
+        };
+        peer.collections = node_info.collections;
+        peer.protocols = node_info.protocols;
+    }
+
+    pairing.write().await.mark_paired(&msg.sender_id, &msg.network_id);
+
+    let our_info = our_node_info(&our_peer_id, &msg.network_id, &networks).await;
+    let (payload, content_codec) = codec::json_payload(serde_json::to_value(&our_info).unwrap_or_default());
+    let mut reply = ProtocolMessage {
+        msg_type: MessageType::PairResponse,
+        network_id: msg.network_id,
+        sender_id: our_peer_id,
+        timestamp: chrono::Utc::now().timestamp(),
+        payload,
+        content_codec,
+        request_id: None,
+        signature: None,
+    };
+    reply.sign(&identity);
+
+    if let Some(sink) = connections.read().await.get(&msg.sender_id).cloned() {
+        let _ = sink.lock().await.send_message(&reply).await;
+    }
+}
+
+/// handle_pair_response completes pairing on the dialing side once the
+/// peer it sent a `PairRequest` to replies: records the peer's claimed
+/// `NodeInfo` and marks it paired for `msg.network_id`. Ignored under the
+/// same conditions as `handle_pair_request` (other than the network-id
+/// check, since only a network this node itself dialed for would have sent
+/// the original `PairRequest`).
+async fn handle_pair_response(msg: ProtocolMessage, peers: Arc<RwLock<HashMap<String, PeerInfo>>>, pairing: Arc<RwLock<PairingTable>>) {
+    let payload = codec::payload_value(msg.content_codec, &msg.payload);
+    let node_info: NodeInfo = match serde_json::from_value(payload) {
+        Ok(info) => info,
+        Err(_) => return,
+    };
+    if node_info.peer_id != msg.sender_id {
+        return;
+    }
+
+    {
+        let mut peers = peers.write().await;
+        let peer = match peers.get_mut(&msg.sender_id) {
+            Some(peer) => peer,
+            None => return,
+        };
+        peer.collections = node_info.collections;
+        peer.protocols = node_info.protocols;
+    }
+
+    pairing.write().await.mark_paired(&msg.sender_id, &msg.network_id);
+}
+
+/// is_gated_message_paired reports whether `msg` is allowed past pairing:
+/// true for every message type except `Operation` and the traffic that can
+/// hand a peer real secrets or collection contents straight out of local
+/// storage (`SyncRequest`/`SyncResponse`/`OpSyncRequest`/`OpSyncResponse`/
+/// `BlockRequest`/`BlockReply`/`ShareRequest`/`ShareDistribute`/
+/// `PostProposal`/`PostSig`), which require `sender_id` to have completed
+/// pairing first (see `crate::pairing`). `Operation` carries its real
+/// `network_id`, and so does the `ReplicationEngine` traffic (`SyncRequest`/
+/// `SyncResponse`/`OpSyncRequest`/`OpSyncResponse`/`BlockRequest`/
+/// `BlockReply` — stamped from `Network::network_id_for_collection` on the
+/// way out, echoed back unchanged on the way back), so both are checked
+/// exactly against that network; otherwise a peer paired only for network A
+/// could read or inject gossip for network B. `ShareRequest`/
+/// `ShareDistribute` (sent by `EncryptionManager::start_decryption_session`
+/// and `DistributedDatabase::distribute_master_key_shares`) go out with an
+/// empty network_id; they, and `post_quorum`'s `PostProposal`/`PostSig`,
+/// only require pairing for at least one network rather than the specific
+/// one.
+async fn is_gated_message_paired(msg: &ProtocolMessage, remote_peer_id: &str, pairing: &Arc<RwLock<PairingTable>>) -> bool {
+    match msg.msg_type {
+        MessageType::Operation
+        | MessageType::SyncRequest
+        | MessageType::SyncResponse
+        | MessageType::OpSyncRequest
+        | MessageType::OpSyncResponse
+        | MessageType::BlockRequest
+        | MessageType::BlockReply => pairing.read().await.is_paired(remote_peer_id, &msg.network_id),
+        MessageType::ShareRequest
+        | MessageType::ShareDistribute
+        | MessageType::PostProposal
+        | MessageType::PostSig => pairing.read().await.is_paired_any(remote_peer_id),
+        _ => true,
+    }
+}
+
+/// handle_connection authenticates a freshly accepted stream with the
+/// server side of the secret handshake, gated on the set of networks this
+/// node currently manages, then promotes the connection to a box-stream.
 async fn handle_connection(
+    mut stream: TcpStream,
+    peers: Arc<RwLock<HashMap<String, PeerInfo>>>,
+    connections: Arc<RwLock<HashMap<String, Arc<Mutex<SecureSink>>>>>,
+    stats: Arc<RwLock<HashMap<String, NetworkStats>>>,
+    handlers: Arc<RwLock<HashMap<MessageType, Vec<MessageHandler>>>>,
+    networks: Arc<RwLock<HashMap<String, NetworkConfig>>>,
+    identity: Arc<LongTermIdentity>,
+    pending_requests: PendingRequests,
+    pairing: Arc<RwLock<PairingTable>>,
+    max_frame_size: usize,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let peer_addr = stream.peer_addr().map(|a| a.to_string()).unwrap_or_default();
+    let known_network_ids: Vec<String> = networks.read().await.keys().cloned().collect();
+
+    let outcome = run_server_handshake(&mut stream, &known_network_ids, &identity).await?;
+
+    {
+        let mut peers = peers.write().await;
+        peers.entry(outcome.remote_peer_id.clone()).or_insert_with(|| PeerInfo {
+            peer_id: outcome.remote_peer_id.clone(),
+            addrs: vec![peer_addr],
+            protocols: vec![],
+            latency: chrono::Duration::zero(),
+            last_seen: chrono::Utc::now(),
+            collections: vec![],
+            supported_codecs: vec![CodecKind::Json],
+        });
+    }
+
+    // An inbound connection doesn't declare which of this node's networks it
+    // dialed for, so the `GetAddr` sent on registration is left unscoped, and
+    // pairing is left to the dialer to initiate (see `register_secure_connection`).
+    let our_peer_id = identity.peer_id();
+    register_secure_connection(stream, outcome, String::new(), our_peer_id, identity, networks, peers, connections, stats, handlers, pending_requests, pairing, max_frame_size).await;
+    Ok(())
+}
+
+/// register_secure_connection splits an authenticated stream, wraps the
+/// write half in a `SecureSink` keyed by the handshake's outbound session
+/// key, stores it under the remote peer id, sends a `GetAddr` so this node
+/// starts learning the rest of the network from its new peer, initiates
+/// pairing for `network_id` if the caller already knows which network this
+/// connection is for (the dialing side always does; an inbound connection
+/// leaves this to the peer that dialed it), and spawns the read loop (keyed
+/// by the inbound session key) for the read half.
+async fn register_secure_connection(
     stream: TcpStream,
+    outcome: HandshakeOutcome,
+    network_id: String,
+    our_peer_id: String,
+    identity: Arc<LongTermIdentity>,
     networks: Arc<RwLock<HashMap<String, NetworkConfig>>>,
     peers: Arc<RwLock<HashMap<String, PeerInfo>>>,
-    connections: Arc<RwLock<HashMap<String, Arc<Mutex<TcpStream>>>>>,
+    connections: Arc<RwLock<HashMap<String, Arc<Mutex<SecureSink>>>>>,
+    stats: Arc<RwLock<HashMap<String, NetworkStats>>>,
     handlers: Arc<RwLock<HashMap<MessageType, Vec<MessageHandler>>>>,
-    peer_id: String,
+    pending_requests: PendingRequests,
+    pairing: Arc<RwLock<PairingTable>>,
+    max_frame_size: usize,
+) {
+    let (read_half, write_half) = tokio::io::split(stream);
+
+    let secure_sink = SecureSink {
+        sink: FramedWrite::new(write_half, wire::new_codec(max_frame_size)),
+        encryptor: BoxStreamEncryptor::new(outcome.send_key),
+    };
+    connections.write().await.insert(outcome.remote_peer_id.clone(), Arc::new(Mutex::new(secure_sink)));
+
+    let mut get_addr = ProtocolMessage {
+        msg_type: MessageType::GetAddr,
+        network_id: network_id.clone(),
+        sender_id: our_peer_id.clone(),
+        timestamp: chrono::Utc::now().timestamp(),
+        payload: Vec::new(),
+        content_codec: CodecKind::Json,
+        request_id: None,
+        signature: None,
+    };
+    get_addr.sign(&identity);
+    if let Some(sink) = connections.read().await.get(&outcome.remote_peer_id).cloned() {
+        let _ = sink.lock().await.send_message(&get_addr).await;
+    }
+
+    if !network_id.is_empty() {
+        send_pair_request(&network_id, &our_peer_id, &identity, &networks, &connections, &outcome.remote_peer_id).await;
+    }
+
+    let source = FramedRead::new(read_half, wire::new_codec(max_frame_size));
+    let decryptor = BoxStreamDecryptor::new(outcome.recv_key);
+
+    let result = read_loop(source, decryptor, outcome.remote_peer_id.clone(), peers, stats, handlers, Arc::clone(&pending_requests), Arc::clone(&pairing), max_frame_size).await;
+
+    // Cancel any requests still waiting on this connection rather than
+    // letting them sit until their timeout fires, and forget any pairing
+    // recorded for it so a later reconnect must pair again.
+    pending_requests.write().await.retain(|_, (pid, _)| pid != &outcome.remote_peer_id);
+    pairing.write().await.forget(&outcome.remote_peer_id);
+
+    if let Err(e) = result {
+        eprintln!("connection to {} closed: {}", outcome.remote_peer_id, e);
+    }
+}
+
+/// send_pair_request builds this node's `NodeInfo` for `network_id` and
+/// sends it as a signed `PairRequest` to `remote_peer_id`. Only the side
+/// that knows which network a connection is for can initiate pairing (see
+/// `register_secure_connection`); the other side replies via
+/// `handle_pair_request` once it receives this.
+async fn send_pair_request(
+    network_id: &str,
+    our_peer_id: &str,
+    identity: &Arc<LongTermIdentity>,
+    networks: &Arc<RwLock<HashMap<String, NetworkConfig>>>,
+    connections: &Arc<RwLock<HashMap<String, Arc<Mutex<SecureSink>>>>>,
+    remote_peer_id: &str,
+) {
+    let node_info = our_node_info(our_peer_id, network_id, networks).await;
+    let (payload, content_codec) = codec::json_payload(serde_json::to_value(&node_info).unwrap_or_default());
+    let mut msg = ProtocolMessage {
+        msg_type: MessageType::PairRequest,
+        network_id: network_id.to_string(),
+        sender_id: our_peer_id.to_string(),
+        timestamp: chrono::Utc::now().timestamp(),
+        payload,
+        content_codec,
+        request_id: None,
+        signature: None,
+    };
+    msg.sign(identity);
+
+    if let Some(sink) = connections.read().await.get(remote_peer_id).cloned() {
+        let _ = sink.lock().await.send_message(&msg).await;
+    }
+}
+
+/// our_node_info builds this node's `NodeInfo` claim for `network_id`: the
+/// collections it currently shares on that network, plus the static
+/// `protocols`/`version` this build advertises.
+async fn our_node_info(our_peer_id: &str, network_id: &str, networks: &Arc<RwLock<HashMap<String, NetworkConfig>>>) -> NodeInfo {
+    let collections: Vec<String> = networks.read().await.get(network_id)
+        .map(|cfg| cfg.collections.keys().cloned().collect())
+        .unwrap_or_default();
+
+    NodeInfo {
+        peer_id: our_peer_id.to_string(),
+        collections,
+        protocols: SUPPORTED_PROTOCOLS.iter().map(|p| p.to_string()).collect(),
+        version: PROTOCOL_VERSION.to_string(),
+    }
+}
+
+/// read_loop reads length-delimited, box-stream-encrypted, MessagePack
+/// chunks off a connection until EOF/error, reassembling multi-frame
+/// messages, updating peer bookkeeping, and dispatching complete messages to
+/// registered handlers via a bounded channel so a slow handler never stalls
+/// the read loop.
+async fn read_loop(
+    mut source: FramedRead<ReadHalf<TcpStream>, LengthDelimitedCodec>,
+    mut decryptor: BoxStreamDecryptor,
+    remote_peer_id: String,
+    peers: Arc<RwLock<HashMap<String, PeerInfo>>>,
+    stats: Arc<RwLock<HashMap<String, NetworkStats>>>,
+    handlers: Arc<RwLock<HashMap<MessageType, Vec<MessageHandler>>>>,
+    pending_requests: PendingRequests,
+    pairing: Arc<RwLock<PairingTable>>,
+    max_frame_size: usize,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    // Simplified for compilation
+    let (dispatch_tx, mut dispatch_rx) = mpsc::channel::<ProtocolMessage>(DISPATCH_CHANNEL_CAPACITY);
+
+    let dispatch_handlers = Arc::clone(&handlers);
+    tokio::spawn(async move {
+        while let Some(msg) = dispatch_rx.recv().await {
+            let handlers = dispatch_handlers.read().await;
+            if let Some(hs) = handlers.get(&msg.msg_type) {
+                for handler in hs {
+                    handler(msg.clone());
+                }
+            }
+        }
+    });
+
+    // Chunked messages (`WireChunk::more`) aren't capped by the codec's
+    // per-frame limit, so the reassembler needs its own ceiling on the
+    // total reassembled size — reusing `max_frame_size` as that ceiling,
+    // since nothing legitimate should need a `ProtocolMessage` bigger than
+    // the largest single frame this connection already accepts.
+    let mut reassembler = MessageReassembler::new(max_frame_size);
+
+    while let Some(frame) = source.next().await {
+        let frame = frame?;
+        let frame_len = frame.len();
+
+        let opened = match decryptor.open(&frame) {
+            Ok(plaintext) => plaintext,
+            Err(e) => {
+                eprintln!("dropping connection to {}: {}", remote_peer_id, e);
+                break; // a failed open means the box-stream is desynced; the connection can't be trusted further
+            }
+        };
+
+        let msg = match reassembler.push(&opened) {
+            Ok(Some(msg)) => msg,
+            Ok(None) => continue, // more chunks to come
+            Err(e) => {
+                eprintln!("discarding malformed message from {}: {}", remote_peer_id, e);
+                continue;
+            }
+        };
+
+        if !msg.verify() {
+            eprintln!("discarding unsigned or forged message from {}: {}", remote_peer_id, msg.msg_type);
+            continue;
+        }
+
+        if !is_gated_message_paired(&msg, &remote_peer_id, &pairing).await {
+            eprintln!("discarding {} from {}: peer hasn't completed pairing", msg.msg_type, remote_peer_id);
+            continue;
+        }
+
+        {
+            let mut peers = peers.write().await;
+            if let Some(peer) = peers.get_mut(&remote_peer_id) {
+                peer.last_seen = chrono::Utc::now();
+            }
+        }
+
+        {
+            let mut stats = stats.write().await;
+            if let Some(stat) = stats.get_mut(&msg.network_id) {
+                stat.operations_received += 1;
+                stat.bytes_transferred += frame_len as i64;
+            }
+        }
+
+        // A message whose request_id matches an in-flight `request()` call is
+        // the reply to it: complete the waiting oneshot instead of handing it
+        // to the general handler dispatch.
+        if let Some(request_id) = msg.request_id {
+            let mut pending = pending_requests.write().await;
+            if let Some((_, tx)) = pending.remove(&request_id) {
+                let _ = tx.send(msg);
+                continue;
+            }
+        }
+
+        if dispatch_tx.send(msg).await.is_err() {
+            break; // dispatch task gone
+        }
+    }
+
     Ok(())
-}
\ No newline at end of file
+}
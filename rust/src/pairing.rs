@@ -0,0 +1,63 @@
+// Per-network pairing. The secret handshake (`crypto::handshake`) already
+// authenticates a connection's long-term identity and, on the accepting
+// side, checks the dialer's HMAC against every network this node knows —
+// but it doesn't record *which* of those networks the peer is actually
+// entitled to exchange `SyncRequest`/`Operation` traffic for, or what that
+// peer claims to serve. Pairing closes that gap: right after a connection
+// is promoted to a box-stream, the dialer (the side that actually knows
+// which network it's joining) sends a `PairRequest` carrying a `NodeInfo`
+// for that network; the acceptor checks the network_id is one it manages,
+// replies with its own `NodeInfo` in a `PairResponse`, and both sides record
+// the peer as paired for that network_id before trusting it with anything
+// beyond discovery traffic. See `network::register_pairing_handlers`.
+
+use std::collections::HashSet;
+
+/// Software version advertised in this node's `NodeInfo` during pairing.
+pub const PROTOCOL_VERSION: &str = "0.1.0";
+
+/// Protocols this build of the node advertises during pairing.
+pub const SUPPORTED_PROTOCOLS: &[&str] = &["knirvbase/1"];
+
+/// PairingTable tracks which `(peer_id, network_id)` pairs have completed
+/// the mutual `NodeInfo` exchange. A plain in-memory set owned by
+/// `NetworkManager`; unlike `discovery::NodeTable` it has no persistence,
+/// and is rebuilt from scratch (by re-pairing) on every restart.
+#[derive(Default)]
+pub struct PairingTable {
+    paired: HashSet<(String, String)>,
+}
+
+impl PairingTable {
+    /// new creates an empty table.
+    pub fn new() -> Self {
+        PairingTable { paired: HashSet::new() }
+    }
+
+    /// mark_paired records that `peer_id` has completed pairing for
+    /// `network_id`. Idempotent.
+    pub fn mark_paired(&mut self, peer_id: &str, network_id: &str) {
+        self.paired.insert((peer_id.to_string(), network_id.to_string()));
+    }
+
+    /// is_paired reports whether `peer_id` has completed pairing for
+    /// `network_id`.
+    pub fn is_paired(&self, peer_id: &str, network_id: &str) -> bool {
+        self.paired.contains(&(peer_id.to_string(), network_id.to_string()))
+    }
+
+    /// is_paired_any reports whether `peer_id` has completed pairing for at
+    /// least one network. Used to gate the message types that are not checked
+    /// against one specific network (`ShareRequest`/`ShareDistribute`/
+    /// `PostProposal`/`PostSig`) — see `network::is_gated_message_paired`.
+    pub fn is_paired_any(&self, peer_id: &str) -> bool {
+        self.paired.iter().any(|(p, _)| p == peer_id)
+    }
+
+    /// forget drops every pairing recorded for `peer_id`, e.g. once its
+    /// connection is gone, so a later reconnect must pair again before its
+    /// traffic is trusted.
+    pub fn forget(&mut self, peer_id: &str) {
+        self.paired.retain(|(p, _)| p != peer_id);
+    }
+}
@@ -1,8 +1,12 @@
+use std::cmp::Ordering;
 use std::collections::HashMap;
-use serde_json;
+use serde_json::Value;
 use crate::database::DistributedDatabase;
 use crate::collection::DistributedCollection;
 
+/// Number of results returned by a `NEAR` query when no `LIMIT` is given.
+const DEFAULT_NEAR_LIMIT: usize = 10;
+
 /// KNIRVQLParser parses KNIRVQL queries
 pub struct KNIRVQLParser;
 
@@ -12,19 +16,253 @@ impl KNIRVQLParser {
         KNIRVQLParser
     }
 
-    /// Parse parses a KNIRVQL query string
+    /// Parse parses a KNIRVQL query string of the form:
+    ///
+    ///   GET <collection> [WHERE <conditions>] [LIMIT <n>]
+    ///   DELETE <collection> [WHERE <conditions>]
+    ///   INSERT <collection> SET <field>=<value>[, <field>=<value>]...
+    ///   UPDATE|SET <collection> SET <field>=<value>[, ...] [WHERE <conditions>]
+    ///
+    /// where `<conditions>` is one or more `<field> <op> <value>` terms
+    /// joined by `AND`/`OR`, `<op>` is `=`, `!=`, `<`, `>`, or `NEAR` (taking
+    /// a `[v1, v2, ...]` vector literal instead of a scalar), and string
+    /// values are double-quoted.
     pub fn parse(&self, query_str: &str) -> Result<Query, Box<dyn std::error::Error + Send + Sync>> {
-        // Simplified parser - just support basic GET queries
-        if query_str.starts_with("GET ") {
-            Ok(Query {
-                query_type: QueryType::Get,
-                collection: "default".to_string(),
-                conditions: HashMap::new(),
-            })
-        } else {
-            Err("unsupported query type".into())
+        let tokens = tokenize(query_str)?;
+        let mut pos = 0;
+
+        let verb = tokens.get(pos).ok_or("empty query")?.to_uppercase();
+        pos += 1;
+
+        let query_type = match verb.as_str() {
+            "GET" => QueryType::Get,
+            "SET" => QueryType::Set,
+            "INSERT" => QueryType::Insert,
+            "UPDATE" => QueryType::Update,
+            "DELETE" => QueryType::Delete,
+            other => return Err(format!("unsupported query verb: {}", other).into()),
+        };
+
+        // Tolerate the optional "INTO"/"FROM" noise words some verbs read
+        // naturally with, e.g. "INSERT INTO memory SET ...".
+        if let Some(next) = tokens.get(pos) {
+            if next.eq_ignore_ascii_case("INTO") || next.eq_ignore_ascii_case("FROM") {
+                pos += 1;
+            }
+        }
+
+        let collection = tokens.get(pos).ok_or("expected collection name")?.clone();
+        pos += 1;
+
+        let mut assignments = HashMap::new();
+        if matches!(query_type, QueryType::Set | QueryType::Insert | QueryType::Update) {
+            if !tokens.get(pos).map_or(false, |t| t.eq_ignore_ascii_case("SET")) {
+                return Err(format!("expected SET after {} {}", verb, collection).into());
+            }
+            pos += 1;
+
+            loop {
+                let assignment = tokens.get(pos).ok_or("expected assignment after SET")?;
+                let (field, value) = parse_assignment(assignment)?;
+                assignments.insert(field, value);
+                pos += 1;
+
+                if tokens.get(pos).map_or(false, |t| t == ",") {
+                    pos += 1;
+                    continue;
+                }
+                break;
+            }
+        }
+
+        let mut conditions = Vec::new();
+        if tokens.get(pos).map_or(false, |t| t.eq_ignore_ascii_case("WHERE")) {
+            pos += 1;
+
+            let mut logic = None;
+            loop {
+                let field = tokens.get(pos).ok_or("expected field in WHERE clause")?.clone();
+                pos += 1;
+                let op_tok = tokens.get(pos).ok_or("expected operator in WHERE clause")?.clone();
+                pos += 1;
+
+                let op = if op_tok.eq_ignore_ascii_case("NEAR") {
+                    let vector = parse_vector(tokens.get(pos).ok_or("expected vector literal after NEAR")?)?;
+                    pos += 1;
+                    Op::Near(vector)
+                } else {
+                    let value = parse_value(tokens.get(pos).ok_or("expected value in WHERE clause")?);
+                    pos += 1;
+                    match op_tok.as_str() {
+                        "=" => Op::Eq(value),
+                        "!=" => Op::Ne(value),
+                        "<" => Op::Lt(value),
+                        ">" => Op::Gt(value),
+                        other => return Err(format!("unsupported operator: {}", other).into()),
+                    }
+                };
+
+                conditions.push(ConditionTerm { logic, field, op });
+
+                match tokens.get(pos).map(|t| t.to_uppercase()) {
+                    Some(t) if t == "AND" => {
+                        logic = Some(LogicalOp::And);
+                        pos += 1;
+                    }
+                    Some(t) if t == "OR" => {
+                        logic = Some(LogicalOp::Or);
+                        pos += 1;
+                    }
+                    _ => break,
+                }
+            }
+        }
+
+        let mut limit = None;
+        if tokens.get(pos).map_or(false, |t| t.eq_ignore_ascii_case("LIMIT")) {
+            pos += 1;
+            let n = tokens.get(pos).ok_or("expected number after LIMIT")?;
+            limit = Some(n.parse::<usize>().map_err(|_| "invalid LIMIT value")?);
+        }
+
+        Ok(Query {
+            query_type,
+            collection,
+            conditions,
+            assignments,
+            limit,
+        })
+    }
+}
+
+/// tokenize splits a query string on whitespace and commas, keeping
+/// double-quoted strings and bracketed vector literals as single tokens.
+fn tokenize(input: &str) -> Result<Vec<String>, Box<dyn std::error::Error + Send + Sync>> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        match chars[i] {
+            c if c.is_whitespace() => i += 1,
+            ',' => {
+                tokens.push(",".to_string());
+                i += 1;
+            }
+            '"' => {
+                let start = i;
+                i += 1;
+                while i < chars.len() && chars[i] != '"' {
+                    i += 1;
+                }
+                if i >= chars.len() {
+                    return Err("unterminated string literal".into());
+                }
+                i += 1; // consume closing quote
+                tokens.push(chars[start..i].iter().collect());
+            }
+            '[' => {
+                let start = i;
+                let mut depth = 1;
+                i += 1;
+                while i < chars.len() && depth > 0 {
+                    match chars[i] {
+                        '[' => depth += 1,
+                        ']' => depth -= 1,
+                        _ => {}
+                    }
+                    i += 1;
+                }
+                if depth != 0 {
+                    return Err("unterminated vector literal".into());
+                }
+                tokens.push(chars[start..i].iter().collect());
+            }
+            _ => {
+                let start = i;
+                while i < chars.len() && !chars[i].is_whitespace() && chars[i] != ',' {
+                    i += 1;
+                }
+                tokens.push(chars[start..i].iter().collect());
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// parse_value converts a single token into a JSON scalar: a double-quoted
+/// token becomes a string (quotes stripped), `true`/`false` become a bool,
+/// a parseable number becomes a number, and anything else is kept as a bare
+/// string.
+fn parse_value(token: &str) -> Value {
+    if token.len() >= 2 && token.starts_with('"') && token.ends_with('"') {
+        return Value::String(token[1..token.len() - 1].to_string());
+    }
+    if let Ok(b) = token.parse::<bool>() {
+        return Value::Bool(b);
+    }
+    if let Ok(n) = token.parse::<f64>() {
+        if let Some(num) = serde_json::Number::from_f64(n) {
+            return Value::Number(num);
         }
     }
+    Value::String(token.to_string())
+}
+
+/// parse_vector parses a `[v1, v2, ...]` literal into its component floats.
+fn parse_vector(token: &str) -> Result<Vec<f64>, Box<dyn std::error::Error + Send + Sync>> {
+    let inner = token
+        .strip_prefix('[')
+        .and_then(|s| s.strip_suffix(']'))
+        .ok_or("expected a vector literal like [0.1, 0.2]")?;
+
+    if inner.trim().is_empty() {
+        return Ok(vec![]);
+    }
+
+    inner
+        .split(',')
+        .map(|v| v.trim().parse::<f64>().map_err(|_| format!("invalid vector component: {}", v).into()))
+        .collect()
+}
+
+/// parse_assignment splits a `field=value` token on its first `=`.
+fn parse_assignment(token: &str) -> Result<(String, Value), Box<dyn std::error::Error + Send + Sync>> {
+    let (field, value) = token.split_once('=').ok_or_else(|| format!("expected field=value, got \"{}\"", token))?;
+    if field.is_empty() {
+        return Err(format!("expected field=value, got \"{}\"", token).into());
+    }
+    Ok((field.to_string(), parse_value(value)))
+}
+
+/// LogicalOp joins a `ConditionTerm` to the conditions evaluated before it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LogicalOp {
+    And,
+    Or,
+}
+
+/// Op is the comparison a `ConditionTerm` applies to its field.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Op {
+    Eq(Value),
+    Ne(Value),
+    Lt(Value),
+    Gt(Value),
+    /// Near marks this field as the target of a k-NN similarity ranking
+    /// rather than a boolean filter; see `rank_by_similarity`.
+    Near(Vec<f64>),
+}
+
+/// ConditionTerm is one `WHERE` clause term. `logic` is `None` for the first
+/// term and `Some` for every term after it, describing how it combines with
+/// the conditions evaluated so far.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConditionTerm {
+    pub logic: Option<LogicalOp>,
+    pub field: String,
+    pub op: Op,
 }
 
 /// Query represents a parsed query
@@ -32,7 +270,9 @@ impl KNIRVQLParser {
 pub struct Query {
     pub query_type: QueryType,
     pub collection: String,
-    pub conditions: HashMap<String, serde_json::Value>,
+    pub conditions: Vec<ConditionTerm>,
+    pub assignments: HashMap<String, Value>,
+    pub limit: Option<usize>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -48,15 +288,342 @@ impl Query {
     /// Execute executes the query
     pub async fn execute(
         &self,
-        db: &DistributedDatabase,
+        _db: &DistributedDatabase,
         collection: &DistributedCollection,
-    ) -> Result<serde_json::Value, Box<dyn std::error::Error + Send + Sync>> {
+    ) -> Result<Value, Box<dyn std::error::Error + Send + Sync>> {
         match self.query_type {
             QueryType::Get => {
                 let docs = collection.find_all().await?;
-                Ok(serde_json::to_value(docs)?)
+                let matched: Vec<_> = docs.into_iter().filter(|doc| matches_conditions(doc, &self.conditions)).collect();
+
+                let near_target = self.conditions.iter().find_map(|c| match &c.op {
+                    Op::Near(vector) => Some(vector.clone()),
+                    _ => None,
+                });
+
+                let results = match near_target {
+                    Some(target) => rank_by_similarity(matched, &target, self.limit.unwrap_or(DEFAULT_NEAR_LIMIT)),
+                    None => match self.limit {
+                        Some(limit) => matched.into_iter().take(limit).collect(),
+                        None => matched,
+                    },
+                };
+
+                Ok(serde_json::to_value(results)?)
+            }
+            QueryType::Insert => {
+                let inserted = collection.insert("", self.assignments.clone()).await?;
+                Ok(serde_json::to_value(inserted)?)
+            }
+            QueryType::Update | QueryType::Set => {
+                let docs = collection.find_all().await?;
+                let mut updated = 0;
+                for doc in docs.into_iter().filter(|doc| matches_conditions(doc, &self.conditions)) {
+                    let id = doc.get("id").and_then(|v| v.as_str()).unwrap_or("").to_string();
+                    if id.is_empty() {
+                        continue;
+                    }
+                    updated += collection.update(&id, self.assignments.clone()).await?;
+                }
+                Ok(serde_json::json!({ "updated": updated }))
+            }
+            QueryType::Delete => {
+                let docs = collection.find_all().await?;
+                let mut deleted = 0;
+                for doc in docs.into_iter().filter(|doc| matches_conditions(doc, &self.conditions)) {
+                    let id = doc.get("id").and_then(|v| v.as_str()).unwrap_or("").to_string();
+                    if id.is_empty() {
+                        continue;
+                    }
+                    deleted += collection.delete(&id).await?;
+                }
+                Ok(serde_json::json!({ "deleted": deleted }))
             }
-            _ => Err("query type not implemented".into()),
         }
     }
-}
\ No newline at end of file
+}
+
+/// get_field looks up `field` in a document's user payload first (where
+/// inserted data such as `source` or `vector` actually lives), falling back
+/// to the document's own top-level fields.
+fn get_field<'a>(doc: &'a HashMap<String, Value>, field: &str) -> Option<&'a Value> {
+    if let Some(Value::Object(payload)) = doc.get("payload") {
+        if let Some(v) = payload.get(field) {
+            return Some(v);
+        }
+    }
+    doc.get(field)
+}
+
+fn values_equal(a: &Value, b: &Value) -> bool {
+    if let (Some(x), Some(y)) = (a.as_f64(), b.as_f64()) {
+        return x == y;
+    }
+    a == b
+}
+
+fn compare_numeric(a: &Value, b: &Value) -> Option<Ordering> {
+    a.as_f64()?.partial_cmp(&b.as_f64()?)
+}
+
+/// evaluate_condition checks one `ConditionTerm` against a document. A
+/// `Near` term is always true here since it's applied as a ranking over the
+/// filtered set afterwards, not as a per-document predicate.
+fn evaluate_condition(doc: &HashMap<String, Value>, term: &ConditionTerm) -> bool {
+    let field_value = get_field(doc, &term.field);
+    match &term.op {
+        Op::Eq(v) => field_value.map_or(false, |fv| values_equal(fv, v)),
+        Op::Ne(v) => field_value.map_or(true, |fv| !values_equal(fv, v)),
+        Op::Lt(v) => compare_numeric(field_value.unwrap_or(&Value::Null), v).map_or(false, |o| o == Ordering::Less),
+        Op::Gt(v) => compare_numeric(field_value.unwrap_or(&Value::Null), v).map_or(false, |o| o == Ordering::Greater),
+        Op::Near(_) => true,
+    }
+}
+
+/// matches_conditions folds `conditions` left to right, each term combining
+/// with the running result via its `logic` (the first term's own logic is
+/// ignored, since there's nothing before it to combine with).
+fn matches_conditions(doc: &HashMap<String, Value>, conditions: &[ConditionTerm]) -> bool {
+    let mut result = true;
+    for (i, term) in conditions.iter().enumerate() {
+        let term_result = evaluate_condition(doc, term);
+        result = match (i, term.logic) {
+            (0, _) => term_result,
+            (_, Some(LogicalOp::Or)) => result || term_result,
+            (_, _) => result && term_result,
+        };
+    }
+    result
+}
+
+/// extract_vector reads a document's similarity vector out of its payload's
+/// `vector` field (a JSON array of numbers).
+fn extract_vector(doc: &HashMap<String, Value>) -> Option<Vec<f64>> {
+    get_field(doc, "vector")?.as_array()?.iter().map(|v| v.as_f64()).collect()
+}
+
+/// cosine_distance is `1 - cosine_similarity`, so smaller means more similar.
+/// Mismatched lengths or zero-magnitude vectors are treated as maximally
+/// dissimilar rather than erroring, so one malformed document can't break
+/// ranking for the rest.
+fn cosine_distance(a: &[f64], b: &[f64]) -> f64 {
+    if a.is_empty() || a.len() != b.len() {
+        return 1.0;
+    }
+
+    let dot: f64 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f64>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f64>().sqrt();
+
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 1.0;
+    }
+
+    1.0 - (dot / (norm_a * norm_b))
+}
+
+/// rank_by_similarity orders `docs` by cosine distance between each
+/// document's `vector` payload field and `target`, nearest first, dropping
+/// documents that carry no usable vector and keeping the top `limit`.
+fn rank_by_similarity(docs: Vec<HashMap<String, Value>>, target: &[f64], limit: usize) -> Vec<HashMap<String, Value>> {
+    let mut scored: Vec<(f64, HashMap<String, Value>)> = docs
+        .into_iter()
+        .filter_map(|doc| {
+            let vector = extract_vector(&doc)?;
+            Some((cosine_distance(&vector, target), doc))
+        })
+        .collect();
+
+    scored.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+    scored.into_iter().take(limit).map(|(_, doc)| doc).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use crate::crypto::handshake::LongTermIdentity;
+    use crate::database::{DistributedDatabase, DistributedDbOptions, DistributedOptions};
+    use crate::network::{MessageHandler, Network};
+    use crate::storage::file::FileStorage;
+    use crate::storage::Storage;
+    use crate::types::{MessageType, NetworkConfig, NetworkStats, PeerInfo, ProtocolMessage};
+
+    /// MockNetwork stands in for `Network` in these tests, which never touch
+    /// the network layer: `Query::execute` only ever reads/writes through
+    /// the `DistributedCollection`/`Storage` it's given, so only
+    /// `get_peer_id`/`identity` — called unconditionally by
+    /// `DistributedCollection::insert`/`update` — need to do anything real.
+    struct MockNetwork {
+        peer_id: String,
+        identity: Arc<LongTermIdentity>,
+    }
+
+    impl MockNetwork {
+        fn new() -> Self {
+            MockNetwork {
+                peer_id: uuid::Uuid::new_v4().to_string(),
+                identity: Arc::new(LongTermIdentity::generate()),
+            }
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl Network for MockNetwork {
+        async fn initialize(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> { Ok(()) }
+        async fn create_network(&self, _cfg: NetworkConfig) -> Result<String, Box<dyn std::error::Error + Send + Sync>> { unimplemented!() }
+        async fn join_network(&self, _network_id: &str, _bootstrap_peers: Vec<String>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> { unimplemented!() }
+        async fn leave_network(&self, _network_id: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> { unimplemented!() }
+        async fn add_collection_to_network(&self, _network_id: &str, _collection_name: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> { unimplemented!() }
+        async fn remove_collection_from_network(&self, _network_id: &str, _collection_name: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> { unimplemented!() }
+        fn get_network_collections(&self, _network_id: &str) -> Vec<String> { vec![] }
+        fn network_id_for_collection(&self, _collection_name: &str) -> Option<String> { None }
+        async fn broadcast_message(&self, _network_id: &str, _msg: ProtocolMessage) -> Result<(), Box<dyn std::error::Error + Send + Sync>> { Ok(()) }
+        async fn send_to_peer(&self, _peer_id: &str, _network_id: &str, _msg: ProtocolMessage) -> Result<(), Box<dyn std::error::Error + Send + Sync>> { Ok(()) }
+        fn on_message(&self, _mt: MessageType, _handler: MessageHandler) {}
+        async fn request(&self, _peer_id: &str, _network_id: &str, _msg: ProtocolMessage) -> Result<ProtocolMessage, Box<dyn std::error::Error + Send + Sync>> { unimplemented!() }
+        async fn sample_peers(&self, _network_id: &str, _n: usize) -> Vec<PeerInfo> { vec![] }
+        fn get_network_stats(&self, _network_id: &str) -> Option<NetworkStats> { None }
+        fn get_networks(&self) -> Vec<NetworkConfig> { vec![] }
+        fn get_peer_id(&self) -> String { self.peer_id.clone() }
+        fn get_peers(&self) -> Vec<PeerInfo> { vec![] }
+        fn identity(&self) -> Arc<LongTermIdentity> { Arc::clone(&self.identity) }
+        fn negotiated_codec(&self, _peer_id: &str) -> crate::codec::CodecKind { crate::codec::CodecKind::Json }
+        async fn shutdown(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> { Ok(()) }
+    }
+
+    async fn test_db() -> (DistributedDatabase, std::path::PathBuf) {
+        let base_dir = std::env::temp_dir().join(format!("knirvbase_query_test_{}", uuid::Uuid::new_v4()));
+        let storage: Arc<dyn Storage> = Arc::new(FileStorage::new(base_dir.to_string_lossy().into_owned()).unwrap());
+        let network: Arc<dyn Network> = Arc::new(MockNetwork::new());
+        let opts = DistributedDbOptions {
+            distributed: DistributedOptions { enabled: false, network_id: String::new(), bootstrap_peers: vec![] },
+            gossip_interval_secs: 0,
+            gossip_fanout: 0,
+        };
+        (DistributedDatabase::new(opts, storage, network).await.unwrap(), base_dir)
+    }
+
+    /// Each comparison operator must filter a GET's matched set the way its
+    /// name implies, reading the compared field out of the inserted
+    /// document's payload.
+    #[tokio::test]
+    async fn get_where_filters_by_each_comparison_operator() {
+        let (db, base_dir) = test_db().await;
+        let coll = db.collection("widgets").await;
+        {
+            let coll = coll.read().await;
+            coll.insert("", [("id".to_string(), Value::String("a".to_string())), ("score".to_string(), serde_json::json!(1))].into_iter().collect()).await.unwrap();
+            coll.insert("", [("id".to_string(), Value::String("b".to_string())), ("score".to_string(), serde_json::json!(2))].into_iter().collect()).await.unwrap();
+            coll.insert("", [("id".to_string(), Value::String("c".to_string())), ("score".to_string(), serde_json::json!(3))].into_iter().collect()).await.unwrap();
+        }
+
+        let parser = KNIRVQLParser::new();
+        let ids = |results: Value| -> Vec<String> {
+            results.as_array().unwrap().iter()
+                .map(|d| d.get("payload").and_then(|p| p.get("id")).and_then(|v| v.as_str()).unwrap().to_string())
+                .collect()
+        };
+
+        let coll = coll.read().await;
+
+        let eq = parser.parse("GET widgets WHERE score = 2").unwrap();
+        assert_eq!(ids(eq.execute(&db, &coll).await.unwrap()), vec!["b"]);
+
+        let ne = parser.parse("GET widgets WHERE score != 2").unwrap();
+        let mut ne_ids = ids(ne.execute(&db, &coll).await.unwrap());
+        ne_ids.sort();
+        assert_eq!(ne_ids, vec!["a", "c"]);
+
+        let lt = parser.parse("GET widgets WHERE score < 2").unwrap();
+        assert_eq!(ids(lt.execute(&db, &coll).await.unwrap()), vec!["a"]);
+
+        let gt = parser.parse("GET widgets WHERE score > 2").unwrap();
+        assert_eq!(ids(gt.execute(&db, &coll).await.unwrap()), vec!["c"]);
+
+        let _ = std::fs::remove_dir_all(&base_dir);
+    }
+
+    /// AND/OR terms fold left to right (see `matches_conditions`): "a OR b
+    /// AND c" is `(a OR b) AND c`, not `a OR (b AND c)`.
+    #[tokio::test]
+    async fn where_and_or_combine_left_to_right() {
+        let (db, base_dir) = test_db().await;
+        let coll = db.collection("widgets").await;
+        {
+            let coll = coll.read().await;
+            // tag=x,on=true / tag=y,on=true / tag=y,on=false
+            coll.insert("", [("id".to_string(), Value::String("a".to_string())), ("tag".to_string(), serde_json::json!("x")), ("on".to_string(), serde_json::json!(true))].into_iter().collect()).await.unwrap();
+            coll.insert("", [("id".to_string(), Value::String("b".to_string())), ("tag".to_string(), serde_json::json!("y")), ("on".to_string(), serde_json::json!(true))].into_iter().collect()).await.unwrap();
+            coll.insert("", [("id".to_string(), Value::String("c".to_string())), ("tag".to_string(), serde_json::json!("y")), ("on".to_string(), serde_json::json!(false))].into_iter().collect()).await.unwrap();
+        }
+
+        let parser = KNIRVQLParser::new();
+        let coll = coll.read().await;
+
+        // (tag = "x" OR tag = "y") AND on = true -> a, b
+        let query = parser.parse(r#"GET widgets WHERE tag = "x" OR tag = "y" AND on = true"#).unwrap();
+        let mut ids: Vec<String> = query.execute(&db, &coll).await.unwrap()
+            .as_array().unwrap().iter()
+            .map(|d| d.get("payload").and_then(|p| p.get("id")).and_then(|v| v.as_str()).unwrap().to_string())
+            .collect();
+        ids.sort();
+        assert_eq!(ids, vec!["a", "b"]);
+
+        let _ = std::fs::remove_dir_all(&base_dir);
+    }
+
+    /// NEAR ranks matched documents by ascending cosine distance to the
+    /// query vector, nearest first, regardless of insertion order.
+    #[tokio::test]
+    async fn near_ranks_results_by_cosine_similarity() {
+        let (db, base_dir) = test_db().await;
+        let coll = db.collection("widgets").await;
+        {
+            let coll = coll.read().await;
+            // far: roughly opposite of [1, 0]; near: identical; mid: orthogonal
+            coll.insert("", [("id".to_string(), Value::String("far".to_string())), ("vector".to_string(), serde_json::json!([-1.0, 0.0]))].into_iter().collect()).await.unwrap();
+            coll.insert("", [("id".to_string(), Value::String("mid".to_string())), ("vector".to_string(), serde_json::json!([0.0, 1.0]))].into_iter().collect()).await.unwrap();
+            coll.insert("", [("id".to_string(), Value::String("near".to_string())), ("vector".to_string(), serde_json::json!([1.0, 0.0]))].into_iter().collect()).await.unwrap();
+        }
+
+        let parser = KNIRVQLParser::new();
+        let coll = coll.read().await;
+        let query = parser.parse("GET widgets WHERE vector NEAR [1.0, 0.0]").unwrap();
+        let ids: Vec<String> = query.execute(&db, &coll).await.unwrap()
+            .as_array().unwrap().iter()
+            .map(|d| d.get("payload").and_then(|p| p.get("id")).and_then(|v| v.as_str()).unwrap().to_string())
+            .collect();
+
+        assert_eq!(ids, vec!["near", "mid", "far"]);
+
+        let _ = std::fs::remove_dir_all(&base_dir);
+    }
+
+    /// INSERT/UPDATE/DELETE must actually mutate the underlying collection,
+    /// not just parse.
+    #[tokio::test]
+    async fn insert_update_delete_mutate_the_collection() {
+        let (db, base_dir) = test_db().await;
+        let coll = db.collection("widgets").await;
+        let parser = KNIRVQLParser::new();
+
+        let insert = parser.parse(r#"INSERT INTO widgets SET id="w1", name="gadget""#).unwrap();
+        insert.execute(&db, &coll.read().await).await.unwrap();
+        assert_eq!(coll.read().await.find_all().await.unwrap().len(), 1);
+
+        let update = parser.parse(r#"UPDATE widgets SET name="widget" WHERE id = "w1""#).unwrap();
+        let updated = update.execute(&db, &coll.read().await).await.unwrap();
+        assert_eq!(updated.get("updated").and_then(|v| v.as_i64()), Some(1));
+
+        let docs = coll.read().await.find_all().await.unwrap();
+        assert_eq!(docs[0].get("payload").and_then(|p| p.get("name")).and_then(|v| v.as_str()), Some("widget"));
+
+        let delete = parser.parse(r#"DELETE widgets WHERE id = "w1""#).unwrap();
+        let deleted = delete.execute(&db, &coll.read().await).await.unwrap();
+        assert_eq!(deleted.get("deleted").and_then(|v| v.as_i64()), Some(1));
+        assert!(coll.read().await.find_all().await.unwrap().is_empty());
+
+        let _ = std::fs::remove_dir_all(&base_dir);
+    }
+}
@@ -0,0 +1,617 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use base64::{engine::general_purpose, Engine as _};
+use rand::RngCore;
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+
+use crate::blockstore;
+use crate::clock::{ComparisonResult, VectorClock};
+use crate::codec;
+use crate::collection::oplog_collection_name;
+use crate::crypto::signable::Signable;
+use crate::network::Network;
+use crate::resolver::CRDTResolver;
+use crate::storage::Storage;
+use crate::types::*;
+
+/// Default interval between anti-entropy gossip rounds.
+pub const DEFAULT_GOSSIP_INTERVAL_SECS: u64 = 30;
+/// Default number of peers reconciled against per gossip round.
+pub const DEFAULT_GOSSIP_FANOUT: usize = 3;
+
+/// ReplicationEngineConfig controls the anti-entropy background task (see
+/// `ReplicationEngine`).
+#[derive(Debug, Clone, Copy)]
+pub struct ReplicationEngineConfig {
+    pub gossip_interval_secs: u64,
+    pub fanout: usize,
+}
+
+impl Default for ReplicationEngineConfig {
+    fn default() -> Self {
+        ReplicationEngineConfig {
+            gossip_interval_secs: DEFAULT_GOSSIP_INTERVAL_SECS,
+            fanout: DEFAULT_GOSSIP_FANOUT,
+        }
+    }
+}
+
+/// ReplicationEngine runs background anti-entropy so divergent replicas
+/// reconcile without a caller having to push updates manually. Modeled on
+/// VPNCloud's engine design: a long-running background task holding shared
+/// state and exchanging messages over the existing transport rather than a
+/// bespoke sync protocol.
+///
+/// Each gossip round this node picks `fanout` random peers and, for every
+/// collection it knows locally, sends a `SyncRequest` digest — the id and
+/// `VectorClock` of every local document in that collection. The peer
+/// receiving the digest (`handle_sync_request`) compares it against its own
+/// copies: any id it's missing, or whose local clock `happens_before` or is
+/// `Concurrent` with the advertised clock, is worth fetching, so it replies
+/// with just that want-list. `happens_before` also holds for equal clocks,
+/// so ids whose copies already match are on the want-list too.
+/// Back on the initiator, those documents are read from local storage and
+/// pushed to the peer as an unsolicited `SyncResponse` (no `request_id`, so
+/// it reaches `handle_sync_push` via the normal handler dispatch instead of
+/// completing a pending request); the peer feeds each one through
+/// `CRDTResolver::merge_documents` and bumps its local clock with
+/// `VectorClock::merge`.
+///
+/// Each round also runs a second, finer-grained reconciliation against the
+/// collection's append-only operation log (see `collection::oplog_collection_name`)
+/// rather than its materialized documents: this node sends its aggregate
+/// per-peer `VectorClock` for the log as an `OpSyncRequest`
+/// (`local_operation_vector`); the peer replies with exactly the
+/// `CRDTOperation`s whose originating peer counter it is behind on
+/// (`handle_op_sync_request`); and this node applies each one idempotently
+/// — merging its document through `CRDTResolver` and appending the
+/// operation itself to its own oplog, so the gap is closed for good and
+/// future rounds' digests reflect it (`apply_operation`). This gives
+/// `DistributedCollection::insert`/`update`/`delete` a reliable, self-healing
+/// delivery path underneath their fire-and-forget `broadcast_operation` call.
+pub struct ReplicationEngine {
+    network: Arc<dyn Network>,
+    storage: Arc<dyn Storage>,
+    resolver: CRDTResolver,
+    config: ReplicationEngineConfig,
+    collections: tokio::sync::RwLock<HashSet<String>>,
+    handler_registered: AtomicBool,
+    task: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl ReplicationEngine {
+    /// NewReplicationEngine creates an engine for `network`/`storage`, idle
+    /// until `start` is called.
+    pub fn new(network: Arc<dyn Network>, storage: Arc<dyn Storage>, config: ReplicationEngineConfig) -> Self {
+        ReplicationEngine {
+            network,
+            storage,
+            resolver: CRDTResolver::new(),
+            config,
+            collections: tokio::sync::RwLock::new(HashSet::new()),
+            handler_registered: AtomicBool::new(false),
+            task: Mutex::new(None),
+        }
+    }
+
+    /// register_collection tells the engine about a locally-known
+    /// collection so future gossip rounds include it. Safe to call
+    /// repeatedly for the same name.
+    pub async fn register_collection(&self, name: &str) {
+        self.collections.write().await.insert(name.to_string());
+    }
+
+    /// Start registers the engine's message handlers (see
+    /// `register_handlers`; idempotent — safe to call more than once) and spawns the periodic gossip task if
+    /// it isn't already running.
+    pub async fn start(self: &Arc<Self>) {
+        self.register_handlers();
+
+        let mut task = self.task.lock().await;
+        if task.is_some() {
+            return; // already running
+        }
+
+        let engine = Arc::clone(self);
+        let interval = std::time::Duration::from_secs(self.config.gossip_interval_secs.max(1));
+        *task = Some(tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                engine.gossip_round().await;
+            }
+        }));
+    }
+
+    /// Stop aborts the background gossip task, if one is running. Safe to
+    /// call even if `start` was never called, or more than once.
+    pub async fn stop(&self) {
+        if let Some(task) = self.task.lock().await.take() {
+            task.abort();
+        }
+    }
+
+    /// register_handlers wires up the process-wide `SyncRequest`/
+    /// `SyncResponse`/`OpSyncRequest`/`BlockRequest` handlers. Idempotent:
+    /// only the first call registers anything.
+    fn register_handlers(self: &Arc<Self>) {
+        if self.handler_registered.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        let engine = Arc::clone(self);
+        self.network.on_message(MessageType::SyncRequest, Box::new(move |msg| {
+            tokio::spawn(handle_sync_request(Arc::clone(&engine), msg));
+        }));
+
+        let engine = Arc::clone(self);
+        self.network.on_message(MessageType::SyncResponse, Box::new(move |msg| {
+            tokio::spawn(handle_sync_push(Arc::clone(&engine), msg));
+        }));
+
+        // OpSyncResponse needs no handler of its own: it always carries the
+        // `request_id` of the `OpSyncRequest` it answers, so `Network::request`
+        // completes the waiting call directly instead of dispatching here.
+        let engine = Arc::clone(self);
+        self.network.on_message(MessageType::OpSyncRequest, Box::new(move |msg| {
+            tokio::spawn(handle_op_sync_request(Arc::clone(&engine), msg));
+        }));
+
+        // BlockReply needs no handler of its own, for the same reason as
+        // OpSyncResponse above.
+        let engine = Arc::clone(self);
+        self.network.on_message(MessageType::BlockRequest, Box::new(move |msg| {
+            tokio::spawn(handle_block_request(Arc::clone(&engine), msg));
+        }));
+    }
+
+    /// gossip_round samples `config.fanout` peers and runs the digest
+    /// exchange against each, for every registered collection.
+    async fn gossip_round(&self) {
+        let peers = sample_peers(&self.network.get_peers(), self.config.fanout);
+        if peers.is_empty() {
+            return;
+        }
+
+        let collections: Vec<String> = self.collections.read().await.iter().cloned().collect();
+        for collection in &collections {
+            // A collection not yet associated with any network (via
+            // `Network::add_collection_to_network`) has nowhere safe to
+            // gossip it to pairing-gated peers, so it sits out this round.
+            let network_id = match self.network.network_id_for_collection(collection) {
+                Some(network_id) => network_id,
+                None => continue,
+            };
+
+            if let Ok(digest) = self.local_digest(collection).await {
+                if !digest.is_empty() {
+                    for peer in &peers {
+                        let _ = self.reconcile_with_peer(collection, &network_id, &digest, &peer.peer_id).await;
+                    }
+                }
+            }
+
+            if let Ok(vector) = self.local_operation_vector(collection).await {
+                for peer in &peers {
+                    let _ = self.reconcile_operations_with_peer(collection, &network_id, &vector, &peer.peer_id).await;
+                }
+            }
+        }
+    }
+
+    /// local_digest reads every document currently stored in `collection`
+    /// and returns its id mapped to its `VectorClock`.
+    async fn local_digest(&self, collection: &str) -> Result<HashMap<String, VectorClock>, Box<dyn std::error::Error + Send + Sync>> {
+        let mut digest = HashMap::new();
+        for doc in self.storage.find_all(collection).await? {
+            let id = match doc.get("id").and_then(|v| v.as_str()) {
+                Some(id) => id.to_string(),
+                None => continue,
+            };
+            let vector: VectorClock = match doc.get("vector").cloned().map(serde_json::from_value) {
+                Some(Ok(vector)) => vector,
+                _ => continue, // not a CRDT-tracked document; nothing to gossip
+            };
+            digest.insert(id, vector);
+        }
+        Ok(digest)
+    }
+
+    /// reconcile_with_peer sends `digest` to `peer_id`, awaits the want-list
+    /// it replies with, and pushes the wanted documents back to it.
+    async fn reconcile_with_peer(
+        &self,
+        collection: &str,
+        network_id: &str,
+        digest: &HashMap<String, VectorClock>,
+        peer_id: &str,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let content_codec = self.network.negotiated_codec(peer_id);
+        let payload = content_codec.encode(&serde_json::json!({ "collection": collection, "digest": digest }))?;
+        let request_msg = ProtocolMessage {
+            msg_type: MessageType::SyncRequest,
+            network_id: network_id.to_string(),
+            sender_id: self.network.get_peer_id(),
+            timestamp: chrono::Utc::now().timestamp(),
+            payload,
+            content_codec,
+            request_id: None,
+            signature: None,
+        };
+
+        let reply = self.network.request(peer_id, network_id, request_msg).await?;
+        let reply_payload = codec::payload_value(reply.content_codec, &reply.payload);
+        let want_ids: Vec<String> = reply_payload.get("want_ids").cloned()
+            .map(serde_json::from_value)
+            .transpose()?
+            .unwrap_or_default();
+
+        if want_ids.is_empty() {
+            return Ok(());
+        }
+
+        let mut documents = Vec::with_capacity(want_ids.len());
+        for id in &want_ids {
+            if let Some(doc) = self.storage.find(collection, id).await? {
+                documents.push(doc);
+            }
+        }
+        if documents.is_empty() {
+            return Ok(());
+        }
+
+        let content_codec = self.network.negotiated_codec(peer_id);
+        let payload = content_codec.encode(&serde_json::json!({ "collection": collection, "documents": documents }))?;
+        let push_msg = ProtocolMessage {
+            msg_type: MessageType::SyncResponse,
+            network_id: network_id.to_string(),
+            sender_id: self.network.get_peer_id(),
+            timestamp: chrono::Utc::now().timestamp(),
+            payload,
+            content_codec,
+            request_id: None,
+            signature: None,
+        };
+        self.network.send_to_peer(peer_id, network_id, push_msg).await
+    }
+
+    /// merge_incoming feeds each of `documents` through `CRDTResolver` and
+    /// persists the result, handling the case where `collection` has no
+    /// local copy of a document yet (the merge is then just the remote
+    /// document).
+    async fn merge_incoming(&self, collection: &str, documents: Vec<DistributedDocument>) {
+        for remote in documents {
+            let local = match self.storage.find(collection, &remote.id).await {
+                Ok(Some(doc)) => serde_json::to_value(&doc).ok()
+                    .and_then(|v| serde_json::from_value::<DistributedDocument>(v).ok()),
+                _ => None,
+            };
+
+            let merged = match local {
+                Some(local) => self.resolver.merge_documents(&local, &remote),
+                None => remote,
+            };
+
+            let storage_doc = match serde_json::to_value(&merged) {
+                Ok(v) => v,
+                Err(_) => continue,
+            };
+            let storage_map = match serde_json::from_value::<HashMap<String, serde_json::Value>>(storage_doc) {
+                Ok(m) => m,
+                Err(_) => continue,
+            };
+            let _ = self.storage.insert(collection, storage_map).await;
+        }
+    }
+
+    /// local_operation_vector folds every operation in `collection`'s
+    /// append-only oplog into a single aggregate `VectorClock` — the
+    /// highest counter this node has observed from each originating peer —
+    /// for exchange during operation-level anti-entropy. Unlike
+    /// `local_digest`, this is one clock per collection rather than one per
+    /// document, matching the granularity operations themselves are
+    /// sequenced at.
+    async fn local_operation_vector(&self, collection: &str) -> Result<VectorClock, Box<dyn std::error::Error + Send + Sync>> {
+        let mut vector = VectorClock::new();
+        for doc in self.storage.find_all(&oplog_collection_name(collection)).await? {
+            if let Ok(operation) = serde_json::from_value::<CRDTOperation>(serde_json::to_value(doc)?) {
+                vector = vector.merge(&operation.vector);
+            }
+        }
+        Ok(vector)
+    }
+
+    /// reconcile_operations_with_peer sends `vector` — this node's
+    /// aggregate view of `collection`'s operation log — to `peer_id` as an
+    /// `OpSyncRequest`, and applies whatever `CRDTOperation`s come back in
+    /// the reply.
+    async fn reconcile_operations_with_peer(
+        &self,
+        collection: &str,
+        network_id: &str,
+        vector: &VectorClock,
+        peer_id: &str,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let content_codec = self.network.negotiated_codec(peer_id);
+        let payload = content_codec.encode(&serde_json::json!({ "collection": collection, "vector": vector }))?;
+        let request_msg = ProtocolMessage {
+            msg_type: MessageType::OpSyncRequest,
+            network_id: network_id.to_string(),
+            sender_id: self.network.get_peer_id(),
+            timestamp: chrono::Utc::now().timestamp(),
+            payload,
+            content_codec,
+            request_id: None,
+            signature: None,
+        };
+
+        let reply = self.network.request(peer_id, network_id, request_msg).await?;
+        let reply_payload = codec::payload_value(reply.content_codec, &reply.payload);
+        let operations: Vec<CRDTOperation> = reply_payload.get("operations").cloned()
+            .map(serde_json::from_value)
+            .transpose()?
+            .unwrap_or_default();
+
+        for operation in operations {
+            self.apply_operation(collection, network_id, operation).await;
+        }
+        Ok(())
+    }
+
+    /// apply_operation folds one remote `CRDTOperation` into local state:
+    /// merging its document (if any) into storage through `CRDTResolver`,
+    /// then appending the operation itself to the local oplog so this
+    /// node's own aggregate vector — and thus future gossip rounds —
+    /// reflects it. Operations carry their originating `(peer_id, counter)`
+    /// vector entry as part of `operation.vector`, so `handle_op_sync_request`
+    /// only ever offers operations the peer's own counter is behind on; an
+    /// operation already present in the local oplog (by `operation.id`) is
+    /// skipped here too, making re-delivery by more than one peer or gossip
+    /// round harmless. If the operation's payload was offloaded
+    /// (`operation.payload_ref`, see `blockstore`) and this node can't
+    /// obtain the block from its own storage or from the operation's
+    /// originating peer, the operation is dropped for now and left for a
+    /// later gossip round to retry. The oplog always keeps the operation in
+    /// the same (possibly offloaded) form it arrived in, so forwarding it
+    /// on to a third peer via `handle_op_sync_request` never re-inflates it.
+    async fn apply_operation(&self, collection: &str, network_id: &str, operation: CRDTOperation) {
+        if !operation.verify() {
+            eprintln!("rejecting operation {} from {}: missing or invalid signature", operation.id, operation.peer_id);
+            return;
+        }
+
+        let oplog_name = oplog_collection_name(collection);
+        if matches!(self.storage.find(&oplog_name, &operation.id).await, Ok(Some(_))) {
+            return;
+        }
+
+        let remote = match &operation.data {
+            Some(data) if operation.payload_ref.is_some() => {
+                match self.resolve_payload_ref(data.clone(), &operation.payload_ref, network_id, &operation.peer_id).await {
+                    Some(resolved) => Some(resolved),
+                    None => {
+                        eprintln!("dropping operation {}: couldn't obtain offloaded payload {:?}", operation.id, operation.payload_ref);
+                        return;
+                    }
+                }
+            }
+            Some(data) => Some(data.clone()),
+            None => None,
+        };
+
+        if let Some(remote) = remote {
+            let local = match self.storage.find(collection, &remote.id).await {
+                Ok(Some(doc)) => serde_json::to_value(&doc).ok()
+                    .and_then(|v| serde_json::from_value::<DistributedDocument>(v).ok()),
+                _ => None,
+            };
+            let merged = match local {
+                Some(local) => self.resolver.merge_documents(&local, &remote),
+                None => remote,
+            };
+            let storage_doc = match serde_json::to_value(&merged) {
+                Ok(v) => serde_json::from_value::<HashMap<String, serde_json::Value>>(v).ok(),
+                Err(_) => None,
+            };
+            if let Some(storage_doc) = storage_doc {
+                let _ = self.storage.insert(collection, storage_doc).await;
+            }
+        }
+
+        if let Ok(v) = serde_json::to_value(&operation) {
+            if let Ok(log_doc) = serde_json::from_value::<HashMap<String, serde_json::Value>>(v) {
+                let _ = self.storage.insert(&oplog_name, log_doc).await;
+            }
+        }
+    }
+
+    /// resolve_payload_ref returns `data` with its `payload` filled in from
+    /// the block named by `payload_ref`: loaded from local storage if this
+    /// node already has it, or fetched from `origin_peer` with
+    /// `fetch_block` otherwise. Returns `None` if the block can't be
+    /// obtained either way.
+    async fn resolve_payload_ref(&self, mut data: DistributedDocument, payload_ref: &Option<String>, network_id: &str, origin_peer: &str) -> Option<DistributedDocument> {
+        let hash = payload_ref.as_ref()?;
+
+        let bytes = match blockstore::load_block(self.storage.as_ref(), hash).await {
+            Ok(Some(bytes)) => bytes,
+            _ => self.fetch_block(origin_peer, network_id, hash).await?,
+        };
+
+        data.payload = Some(serde_json::from_slice(&bytes).ok()?);
+        Some(data)
+    }
+
+    /// fetch_block asks `peer_id` for the block holding `hash` via
+    /// `BlockRequest`/`BlockReply`, and on success persists it locally
+    /// under the same hash so future operations referencing it are
+    /// resolved from storage instead of the network.
+    async fn fetch_block(&self, peer_id: &str, network_id: &str, hash: &str) -> Option<Vec<u8>> {
+        let content_codec = self.network.negotiated_codec(peer_id);
+        let payload = content_codec.encode(&serde_json::json!({ "hash": hash })).ok()?;
+        let request_msg = ProtocolMessage {
+            msg_type: MessageType::BlockRequest,
+            network_id: network_id.to_string(),
+            sender_id: self.network.get_peer_id(),
+            timestamp: chrono::Utc::now().timestamp(),
+            payload,
+            content_codec,
+            request_id: None,
+            signature: None,
+        };
+
+        let reply = self.network.request(peer_id, network_id, request_msg).await.ok()?;
+        let reply_payload = codec::payload_value(reply.content_codec, &reply.payload);
+        let data = reply_payload.get("data").and_then(|v| v.as_str())?;
+        let bytes = general_purpose::STANDARD.decode(data).ok()?;
+
+        let _ = blockstore::store_block(self.storage.as_ref(), &bytes).await;
+        Some(bytes)
+    }
+}
+
+/// handle_sync_request answers an inbound digest with the ids the recipient
+/// needs: ones it's missing entirely, plus ones whose local clock
+/// `happens_before` or is `Concurrent` with the advertised clock. See
+/// `ReplicationEngine`.
+async fn handle_sync_request(engine: Arc<ReplicationEngine>, msg: ProtocolMessage) {
+    let payload = codec::payload_value(msg.content_codec, &msg.payload);
+    let collection = match payload.get("collection").and_then(|v| v.as_str()) {
+        Some(c) => c.to_string(),
+        None => return,
+    };
+    let remote_digest: HashMap<String, VectorClock> = match payload.get("digest").cloned().map(serde_json::from_value) {
+        Some(Ok(d)) => d,
+        _ => return,
+    };
+
+    let local_digest = engine.local_digest(&collection).await.unwrap_or_default();
+
+    let want_ids: Vec<String> = remote_digest.into_iter()
+        .filter(|(id, remote_vector)| {
+            match local_digest.get(id) {
+                None => true,
+                Some(local_vector) => {
+                    local_vector.happens_before(remote_vector)
+                        || local_vector.compare(remote_vector) == ComparisonResult::Concurrent
+                }
+            }
+        })
+        .map(|(id, _)| id)
+        .collect();
+
+    let content_codec = engine.network.negotiated_codec(&msg.sender_id);
+    let reply = ProtocolMessage {
+        msg_type: MessageType::SyncResponse,
+        network_id: msg.network_id.clone(),
+        sender_id: engine.network.get_peer_id(),
+        timestamp: chrono::Utc::now().timestamp(),
+        payload: content_codec.encode(&serde_json::json!({ "want_ids": want_ids })).unwrap_or_default(),
+        content_codec,
+        request_id: msg.request_id,
+        signature: None,
+    };
+    let _ = engine.network.send_to_peer(&msg.sender_id, &msg.network_id, reply).await;
+}
+
+/// handle_sync_push merges an unsolicited batch of documents pushed by a
+/// peer in response to a want-list. See `ReplicationEngine`.
+async fn handle_sync_push(engine: Arc<ReplicationEngine>, msg: ProtocolMessage) {
+    let payload = codec::payload_value(msg.content_codec, &msg.payload);
+    let collection = match payload.get("collection").and_then(|v| v.as_str()) {
+        Some(c) => c.to_string(),
+        None => return,
+    };
+    let documents: Vec<DistributedDocument> = match payload.get("documents").cloned().map(serde_json::from_value) {
+        Some(Ok(docs)) => docs,
+        _ => return,
+    };
+
+    engine.merge_incoming(&collection, documents).await;
+}
+
+/// handle_op_sync_request answers an inbound operation-log digest with
+/// exactly the `CRDTOperation`s the sender's aggregate vector shows it
+/// hasn't seen yet: an operation is missing for the sender if its own
+/// counter for that operation's originating peer (`operation.peer_id`) is
+/// behind the counter the operation itself carries. See
+/// `ReplicationEngine`.
+async fn handle_op_sync_request(engine: Arc<ReplicationEngine>, msg: ProtocolMessage) {
+    let payload = codec::payload_value(msg.content_codec, &msg.payload);
+    let collection = match payload.get("collection").and_then(|v| v.as_str()) {
+        Some(c) => c.to_string(),
+        None => return,
+    };
+    let remote_vector: VectorClock = match payload.get("vector").cloned().map(serde_json::from_value) {
+        Some(Ok(v)) => v,
+        _ => return,
+    };
+
+    let operations: Vec<CRDTOperation> = engine.storage.find_all(&oplog_collection_name(&collection)).await
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|doc| serde_json::from_value(serde_json::to_value(doc).ok()?).ok())
+        .filter(|operation: &CRDTOperation| {
+            let remote_count = remote_vector.0.get(&operation.peer_id).copied().unwrap_or(0);
+            let local_count = operation.vector.0.get(&operation.peer_id).copied().unwrap_or(0);
+            remote_count < local_count
+        })
+        .collect();
+
+    let content_codec = engine.network.negotiated_codec(&msg.sender_id);
+    let reply = ProtocolMessage {
+        msg_type: MessageType::OpSyncResponse,
+        network_id: msg.network_id.clone(),
+        sender_id: engine.network.get_peer_id(),
+        timestamp: chrono::Utc::now().timestamp(),
+        payload: content_codec.encode(&serde_json::json!({ "operations": operations })).unwrap_or_default(),
+        content_codec,
+        request_id: msg.request_id,
+        signature: None,
+    };
+    let _ = engine.network.send_to_peer(&msg.sender_id, &msg.network_id, reply).await;
+}
+
+/// handle_block_request answers an inbound `BlockRequest` with the
+/// requested hash's bytes, base64-encoded, if this node holds that block
+/// (silently dropped otherwise — the requester tried one peer and will
+/// simply get no reply). See `ReplicationEngine::fetch_block`.
+async fn handle_block_request(engine: Arc<ReplicationEngine>, msg: ProtocolMessage) {
+    let payload = codec::payload_value(msg.content_codec, &msg.payload);
+    let hash = match payload.get("hash").and_then(|v| v.as_str()) {
+        Some(hash) => hash.to_string(),
+        None => return,
+    };
+
+    let bytes = match blockstore::load_block(engine.storage.as_ref(), &hash).await {
+        Ok(Some(bytes)) => bytes,
+        _ => return,
+    };
+
+    let content_codec = engine.network.negotiated_codec(&msg.sender_id);
+    let reply = ProtocolMessage {
+        msg_type: MessageType::BlockReply,
+        network_id: msg.network_id.clone(),
+        sender_id: engine.network.get_peer_id(),
+        timestamp: chrono::Utc::now().timestamp(),
+        payload: content_codec.encode(&serde_json::json!({ "data": general_purpose::STANDARD.encode(&bytes) })).unwrap_or_default(),
+        content_codec,
+        request_id: msg.request_id,
+        signature: None,
+    };
+    let _ = engine.network.send_to_peer(&msg.sender_id, &msg.network_id, reply).await;
+}
+
+/// sample_peers picks up to `n` distinct peers at random from `peers`.
+fn sample_peers(peers: &[PeerInfo], n: usize) -> Vec<PeerInfo> {
+    let mut pool = peers.to_vec();
+    let take = n.min(pool.len());
+    let mut sampled = Vec::with_capacity(take);
+    for _ in 0..take {
+        let idx = (rand::rngs::OsRng.next_u32() as usize) % pool.len();
+        sampled.push(pool.remove(idx));
+    }
+    sampled
+}
@@ -0,0 +1,171 @@
+use base64::{engine::general_purpose, Engine as _};
+use serde::{Deserialize, Serialize};
+
+use super::Storage;
+
+/// Chunk size used when splitting a blob for content-addressed storage.
+/// Chosen to keep individual chunk documents small while still getting
+/// useful dedup on repeated/overlapping MEMORY payloads.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Storage collection holding one document per distinct chunk, keyed by its
+/// BLAKE3 hex digest: `{ id: <hex-hash>, data: <base64>, ref_count: u64 }`.
+/// Shared across every collection and every `Storage` backend, so identical
+/// chunks are only ever written once no matter where they came from.
+const BLOB_CHUNKS_COLLECTION: &str = "_blob_chunks";
+
+/// BlobManifest is what gets stored as a document's `blobRef`: an ordered
+/// list of chunk hashes plus the blob's total byte length, enough for
+/// `load_blob` to reassemble it deterministically.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct BlobManifest {
+    chunks: Vec<String>,
+    len: usize,
+}
+
+/// save_blob splits `blob`'s serialized bytes into `CHUNK_SIZE` pieces,
+/// writes each distinct chunk once under its BLAKE3 hash (bumping
+/// `ref_count` if it already exists), and returns the manifest to store as
+/// the document's `blobRef`.
+pub(crate) async fn save_blob(storage: &dyn Storage, blob: &serde_json::Value) -> Result<serde_json::Value, Box<dyn std::error::Error + Send + Sync>> {
+    let bytes = serde_json::to_vec(blob)?;
+    let mut chunks = Vec::with_capacity(bytes.len() / CHUNK_SIZE + 1);
+
+    for piece in bytes.chunks(CHUNK_SIZE.max(1)) {
+        let hash = blake3::hash(piece).to_hex().to_string();
+        bump_ref_count(storage, &hash, piece).await?;
+        chunks.push(hash);
+    }
+
+    let manifest = BlobManifest { chunks, len: bytes.len() };
+    Ok(serde_json::to_value(manifest)?)
+}
+
+/// load_blob reassembles a blob from the manifest previously returned by
+/// `save_blob`, in chunk order.
+pub(crate) async fn load_blob(storage: &dyn Storage, blob_ref: &serde_json::Value) -> Result<serde_json::Value, Box<dyn std::error::Error + Send + Sync>> {
+    let manifest: BlobManifest = serde_json::from_value(blob_ref.clone())?;
+
+    let mut bytes = Vec::with_capacity(manifest.len);
+    for hash in &manifest.chunks {
+        let chunk = storage.find(BLOB_CHUNKS_COLLECTION, hash).await?
+            .ok_or_else(|| format!("missing blob chunk {}", hash))?;
+        let data = chunk.get("data").and_then(|v| v.as_str()).ok_or("blob chunk missing data")?;
+        bytes.extend(general_purpose::STANDARD.decode(data)?);
+    }
+
+    Ok(serde_json::from_slice(&bytes)?)
+}
+
+/// delete_blob decrements the reference count of every chunk `blob_ref`
+/// points at, deleting a chunk once nothing references it any longer. Safe
+/// to call with `None` (a document with no blob).
+pub(crate) async fn delete_blob(storage: &dyn Storage, blob_ref: Option<&serde_json::Value>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let blob_ref = match blob_ref {
+        Some(blob_ref) => blob_ref,
+        None => return Ok(()),
+    };
+    let manifest: BlobManifest = match serde_json::from_value(blob_ref.clone()) {
+        Ok(manifest) => manifest,
+        Err(_) => return Ok(()), // not a manifest we recognize; nothing to reclaim
+    };
+
+    for hash in &manifest.chunks {
+        drop_ref_count(storage, hash).await?;
+    }
+
+    Ok(())
+}
+
+async fn bump_ref_count(storage: &dyn Storage, hash: &str, piece: &[u8]) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let ref_count = match storage.find(BLOB_CHUNKS_COLLECTION, hash).await? {
+        Some(existing) => existing.get("ref_count").and_then(|v| v.as_u64()).unwrap_or(0) + 1,
+        None => 1,
+    };
+
+    let mut doc = std::collections::HashMap::new();
+    doc.insert("id".to_string(), serde_json::Value::String(hash.to_string()));
+    doc.insert("data".to_string(), serde_json::Value::String(general_purpose::STANDARD.encode(piece)));
+    doc.insert("ref_count".to_string(), serde_json::Value::from(ref_count));
+    storage.insert(BLOB_CHUNKS_COLLECTION, doc).await
+}
+
+async fn drop_ref_count(storage: &dyn Storage, hash: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let existing = match storage.find(BLOB_CHUNKS_COLLECTION, hash).await? {
+        Some(existing) => existing,
+        None => return Ok(()), // already gone
+    };
+
+    let ref_count = existing.get("ref_count").and_then(|v| v.as_u64()).unwrap_or(1);
+    if ref_count <= 1 {
+        return storage.delete(BLOB_CHUNKS_COLLECTION, hash).await;
+    }
+
+    let mut doc = existing;
+    doc.insert("ref_count".to_string(), serde_json::Value::from(ref_count - 1));
+    storage.insert(BLOB_CHUNKS_COLLECTION, doc).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::file::FileStorage;
+
+    fn test_storage() -> (FileStorage, std::path::PathBuf) {
+        let base_dir = std::env::temp_dir().join(format!("knirvbase_blob_test_{}", uuid::Uuid::new_v4()));
+        (FileStorage::new(base_dir.to_string_lossy().into_owned()).unwrap(), base_dir)
+    }
+
+    /// save_blob/load_blob must round-trip a blob spanning several chunks
+    /// (bigger than CHUNK_SIZE), and identical content saved twice must
+    /// dedupe onto the same chunk with its ref_count bumped rather than
+    /// writing a duplicate.
+    #[tokio::test]
+    async fn save_and_load_blob_round_trips_and_dedupes_identical_content() {
+        let (storage, base_dir) = test_storage();
+        let blob = serde_json::Value::String("x".repeat(CHUNK_SIZE * 2 + 17));
+
+        let ref_a = save_blob(&storage, &blob).await.unwrap();
+        let manifest_a: BlobManifest = serde_json::from_value(ref_a.clone()).unwrap();
+        assert_eq!(manifest_a.chunks.len(), 3);
+
+        let ref_b = save_blob(&storage, &blob).await.unwrap();
+        let manifest_b: BlobManifest = serde_json::from_value(ref_b.clone()).unwrap();
+        assert_eq!(manifest_a.chunks, manifest_b.chunks, "identical content should hash to the same chunks");
+
+        for hash in &manifest_a.chunks {
+            let chunk = storage.find(BLOB_CHUNKS_COLLECTION, hash).await.unwrap().unwrap();
+            assert_eq!(chunk.get("ref_count").and_then(|v| v.as_u64()), Some(2));
+        }
+
+        let loaded = load_blob(&storage, &ref_a).await.unwrap();
+        assert_eq!(loaded, blob);
+
+        let _ = std::fs::remove_dir_all(&base_dir);
+    }
+
+    /// delete_blob must only actually remove a chunk once every referencing
+    /// blob has released it; a chunk still referenced by a surviving blob
+    /// must stay put.
+    #[tokio::test]
+    async fn delete_blob_reclaims_chunks_only_once_unreferenced() {
+        let (storage, base_dir) = test_storage();
+        let blob = serde_json::Value::String("y".repeat(CHUNK_SIZE + 1));
+
+        let ref_a = save_blob(&storage, &blob).await.unwrap();
+        let ref_b = save_blob(&storage, &blob).await.unwrap();
+        let manifest: BlobManifest = serde_json::from_value(ref_a.clone()).unwrap();
+
+        delete_blob(&storage, Some(&ref_a)).await.unwrap();
+        for hash in &manifest.chunks {
+            assert!(storage.find(BLOB_CHUNKS_COLLECTION, hash).await.unwrap().is_some(), "chunk {} still referenced by the second blob should survive", hash);
+        }
+
+        delete_blob(&storage, Some(&ref_b)).await.unwrap();
+        for hash in &manifest.chunks {
+            assert!(storage.find(BLOB_CHUNKS_COLLECTION, hash).await.unwrap().is_none(), "chunk {} should be reclaimed once unreferenced", hash);
+        }
+
+        let _ = std::fs::remove_dir_all(&base_dir);
+    }
+}
@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+use crate::crypto::pqc::EncryptionManager;
+
+mod blob;
+pub mod file;
+pub mod s3;
+
+pub use file::FileStorage;
+pub use s3::S3Storage;
+
+/// Collections whose sensitive fields are encrypted at rest (see
+/// `is_sensitive_field`). Shared by every `Storage` implementor so they stay
+/// encrypted no matter which backend a collection is persisted to.
+pub(crate) const ENCRYPTED_COLLECTIONS: [&str; 6] = [
+    "credentials",
+    "pqc_keys",
+    "sessions",
+    "audit_log",
+    "threat_events",
+    "access_control",
+];
+
+/// Storage interface for persistence
+#[async_trait::async_trait]
+pub trait Storage: Send + Sync {
+    async fn insert(&self, collection: &str, doc: HashMap<String, serde_json::Value>) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+    async fn update(&self, collection: &str, id: &str, update: HashMap<String, serde_json::Value>) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+    async fn delete(&self, collection: &str, id: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+    async fn find(&self, collection: &str, id: &str) -> Result<Option<HashMap<String, serde_json::Value>>, Box<dyn std::error::Error + Send + Sync>>;
+    async fn find_all(&self, collection: &str) -> Result<Vec<HashMap<String, serde_json::Value>>, Box<dyn std::error::Error + Send + Sync>>;
+}
+
+/// IndexType represents the type of index
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum IndexType {
+    BTree,
+    GIN,
+    HNSW,
+}
+
+/// Index represents a secondary index
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Index {
+    pub name: String,
+    pub collection: String,
+    pub index_type: IndexType,
+    pub fields: Vec<String>,
+    pub unique: bool,
+    pub partial_expr: Option<String>,
+    pub options: Option<HashMap<String, serde_json::Value>>,
+}
+
+/// is_encrypted_collection checks if a collection contains sensitive data.
+/// Shared across every `Storage` backend.
+pub(crate) fn is_encrypted_collection(collection: &str) -> bool {
+    ENCRYPTED_COLLECTIONS.contains(&collection)
+}
+
+fn is_sensitive_field(collection: &str, field_name: &str) -> bool {
+    let sensitive_fields: HashMap<&str, Vec<&str>> = [
+        ("credentials", vec!["hash", "salt"]),
+        ("pqc_keys", vec!["kyber_private_key", "dilithium_private_key"]),
+        ("sessions", vec!["token_hash"]),
+        ("audit_log", vec!["details"]),
+        ("threat_events", vec!["indicators"]),
+        ("access_control", vec!["permissions"]),
+    ].into_iter().collect();
+
+    if let Some(fields) = sensitive_fields.get(collection) {
+        fields.contains(&field_name)
+    } else {
+        false
+    }
+}
+
+/// encrypt_document encrypts the sensitive fields of `doc`'s payload under
+/// the current master key, tagging it with `encrypted`/`encryption_key_id`
+/// so `decrypt_document` (and `FileStorage::raw_encryption_key_id`) can find
+/// it again later. Shared by every `Storage` backend so encrypted
+/// collections stay encrypted at rest regardless of where they're persisted.
+pub(crate) async fn encrypt_document(
+    encryption_mgr: &RwLock<EncryptionManager>,
+    collection: &str,
+    doc: &mut HashMap<String, serde_json::Value>,
+    storage: &dyn Storage,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let master_key = encryption_mgr.read().await.get_master_key();
+    if master_key.is_none() {
+        return Err("no master key set for encryption".into());
+    }
+
+    if let Some(serde_json::Value::Object(payload)) = doc.get_mut("payload") {
+        let encrypted_payload = encrypt_payload(encryption_mgr, collection, payload, &master_key.as_ref().unwrap().id, storage).await?;
+        doc.insert("payload".to_string(), encrypted_payload);
+        doc.insert("encrypted".to_string(), serde_json::Value::Bool(true));
+        doc.insert("encryption_key_id".to_string(), serde_json::Value::String(master_key.unwrap().id));
+    }
+
+    Ok(())
+}
+
+async fn encrypt_payload(
+    encryption_mgr: &RwLock<EncryptionManager>,
+    collection: &str,
+    payload: &serde_json::Map<String, serde_json::Value>,
+    key_id: &str,
+    storage: &dyn Storage,
+) -> Result<serde_json::Value, Box<dyn std::error::Error + Send + Sync>> {
+    let mut encrypted = serde_json::Map::new();
+
+    for (key, value) in payload {
+        if is_sensitive_field(collection, key) {
+            let value_bytes = serde_json::to_vec(value)?;
+            let encrypted_value = encryption_mgr.read().await.encrypt_data(&value_bytes, key_id, storage).await?;
+            encrypted.insert(key.clone(), serde_json::Value::String(encrypted_value));
+            encrypted.insert(format!("{}_encrypted", key), serde_json::Value::Bool(true));
+        } else {
+            encrypted.insert(key.clone(), value.clone());
+        }
+    }
+
+    Ok(serde_json::Value::Object(encrypted))
+}
+
+/// decrypt_document is the counterpart to `encrypt_document`. Shared by
+/// every `Storage` backend.
+pub(crate) async fn decrypt_document(
+    encryption_mgr: &RwLock<EncryptionManager>,
+    doc: &mut HashMap<String, serde_json::Value>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    if let Some(serde_json::Value::Object(payload)) = doc.get_mut("payload") {
+        let decrypted_payload = decrypt_payload(encryption_mgr, payload).await?;
+        doc.insert("payload".to_string(), decrypted_payload);
+    }
+
+    doc.remove("encrypted");
+    doc.remove("encryption_key_id");
+
+    Ok(())
+}
+
+async fn decrypt_payload(
+    encryption_mgr: &RwLock<EncryptionManager>,
+    payload: &serde_json::Map<String, serde_json::Value>,
+) -> Result<serde_json::Value, Box<dyn std::error::Error + Send + Sync>> {
+    let mut decrypted = serde_json::Map::new();
+
+    for (key, value) in payload {
+        if key.ends_with("_encrypted") {
+            continue;
+        }
+
+        if let Some(serde_json::Value::Bool(true)) = payload.get(&format!("{}_encrypted", key)) {
+            if let serde_json::Value::String(encrypted_value) = value {
+                let decrypted_bytes = encryption_mgr.read().await.decrypt_data(encrypted_value)?;
+                let decrypted_value: serde_json::Value = serde_json::from_slice(&decrypted_bytes)?;
+                decrypted.insert(key.clone(), decrypted_value);
+            }
+        } else {
+            decrypted.insert(key.clone(), value.clone());
+        }
+    }
+
+    Ok(serde_json::Value::Object(decrypted))
+}
+
+pub(crate) fn deep_copy_doc(doc: &HashMap<String, serde_json::Value>) -> HashMap<String, serde_json::Value> {
+    serde_json::from_value(serde_json::to_value(doc).unwrap()).unwrap()
+}
@@ -0,0 +1,263 @@
+use std::collections::HashMap;
+use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_s3::Client;
+use tokio::sync::RwLock;
+use crate::crypto::pqc::{EncryptionManager, PQCKeyPair, RotationPolicy};
+use crate::time::TimeSource;
+use super::blob::{delete_blob, load_blob, save_blob};
+use super::{decrypt_document, deep_copy_doc, encrypt_document, is_encrypted_collection, Storage};
+
+/// S3Storage implements Storage against an S3-compatible object store (AWS
+/// S3, MinIO, Garage, ...) so `DistributedCollection` replicas can share
+/// durable storage instead of each node keeping its own local filesystem —
+/// see `FileStorage` for the single-node equivalent. Unlike `FileStorage`,
+/// it does not compress documents, stamp them with a `schema_version`, or
+/// keep a per-collection `_meta` document.
+///
+/// Each collection maps to a key prefix: a document id `<id>` in collection
+/// `<collection>` lives at object key `<collection>/<id>.json`. MEMORY blobs
+/// are content-addressed and chunked by `storage::blob` into the shared
+/// `_blob_chunks` collection rather than stored as a single object per
+/// document.
+pub struct S3Storage {
+    client: Client,
+    bucket: String,
+    encryption_mgr: RwLock<EncryptionManager>,
+}
+
+impl S3Storage {
+    /// NewS3Storage wraps an already-configured `aws_sdk_s3::Client` and the
+    /// bucket to persist into, which must already exist. For MinIO/Garage,
+    /// build `client` from an `aws_sdk_s3::Config` with `.endpoint_url(...)`
+    /// and `.force_path_style(true)` pointed at the cluster.
+    pub fn new(client: Client, bucket: String) -> Self {
+        S3Storage {
+            client,
+            bucket,
+            encryption_mgr: RwLock::new(EncryptionManager::new()),
+        }
+    }
+
+    fn doc_key(&self, collection: &str, id: &str) -> String {
+        format!("{}/{}.json", collection, id)
+    }
+
+    /// raw_blob_ref reads a document straight off the object store, without
+    /// going through `find`'s decrypt/blob-load, just far enough to see the
+    /// content-addressed manifest (if any) it was last saved under.
+    /// `insert` needs this to release the previous blobRef's chunks before
+    /// installing a new one.
+    async fn raw_blob_ref(&self, collection: &str, id: &str) -> Result<Option<serde_json::Value>, Box<dyn std::error::Error + Send + Sync>> {
+        match self.get_object(&self.doc_key(collection, id)).await? {
+            Some(data) => {
+                let doc: HashMap<String, serde_json::Value> = serde_json::from_slice(&data)?;
+                Ok(doc.get("payload").and_then(|p| p.get("blobRef")).cloned())
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// SetMasterKey sets the master PQC key for encryption
+    pub async fn set_master_key(&self, key_pair: PQCKeyPair) {
+        self.encryption_mgr.write().await.set_master_key(key_pair);
+    }
+
+    /// IsEncryptedCollection checks if a collection contains sensitive data
+    pub fn is_encrypted_collection(&self, collection: &str) -> bool {
+        is_encrypted_collection(collection)
+    }
+
+    /// RotateKey retires `key_id` and promotes its successor (see
+    /// `EncryptionManager::rotate_key`), returning the successor.
+    pub async fn rotate_key(&self, key_id: &str) -> Result<PQCKeyPair, Box<dyn std::error::Error + Send + Sync>> {
+        self.encryption_mgr.write().await.rotate_key(key_id, self).await
+    }
+
+    /// SetRotationPolicy replaces the policy that decides when a key is due
+    /// for rotation (see `crate::crypto::pqc::RotationPolicy`).
+    pub async fn set_rotation_policy(&self, policy: RotationPolicy) {
+        self.encryption_mgr.write().await.set_rotation_policy(policy);
+    }
+
+    /// SetTimeSource swaps in the given `TimeSource` for the underlying
+    /// `EncryptionManager`, e.g. a `MockTimeSource` in tests that need
+    /// deterministic key expiry/rotation timing.
+    pub async fn set_time_source(&self, time_source: std::sync::Arc<dyn TimeSource>) {
+        self.encryption_mgr.write().await.set_time_source(time_source);
+    }
+
+    async fn put_object(&self, key: &str, data: Vec<u8>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .body(ByteStream::from(data))
+            .send()
+            .await?;
+        Ok(())
+    }
+
+    async fn get_object(&self, key: &str) -> Result<Option<Vec<u8>>, Box<dyn std::error::Error + Send + Sync>> {
+        match self.client.get_object().bucket(&self.bucket).key(key).send().await {
+            Ok(output) => {
+                let data = output.body.collect().await?.into_bytes().to_vec();
+                Ok(Some(data))
+            }
+            Err(err) if is_not_found(&err) => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    async fn delete_object(&self, key: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.client.delete_object().bucket(&self.bucket).key(key).send().await?;
+        Ok(())
+    }
+
+    /// list_doc_ids lists every document id directly under
+    /// `<collection>/`, i.e. every `<collection>/<id>.json` key, ignoring
+    /// any key nested deeper under that prefix. Paginates with
+    /// `list_objects_v2`'s continuation token.
+    async fn list_doc_ids(&self, collection: &str) -> Result<Vec<String>, Box<dyn std::error::Error + Send + Sync>> {
+        let prefix = format!("{}/", collection);
+        let mut ids = Vec::new();
+        let mut continuation_token = None;
+
+        loop {
+            let mut request = self.client.list_objects_v2().bucket(&self.bucket).prefix(&prefix);
+            if let Some(token) = &continuation_token {
+                request = request.continuation_token(token);
+            }
+            let output = request.send().await?;
+
+            for object in output.contents() {
+                if let Some(key) = object.key() {
+                    if let Some(id) = key.strip_prefix(&prefix).and_then(|rest| rest.strip_suffix(".json")).filter(|id| !id.contains('/')) {
+                        ids.push(id.to_string());
+                    }
+                }
+            }
+
+            continuation_token = output.next_continuation_token().map(|s| s.to_string());
+            if continuation_token.is_none() {
+                break;
+            }
+        }
+
+        Ok(ids)
+    }
+}
+
+/// is_not_found reports whether an S3 `GetObject` error is a missing-key
+/// (`NoSuchKey`) error rather than something worth surfacing, so `get_object`
+/// can treat it the same way `FileStorage::find` treats a missing file.
+fn is_not_found(err: &aws_sdk_s3::error::SdkError<aws_sdk_s3::operation::get_object::GetObjectError>) -> bool {
+    matches!(
+        err.as_service_error(),
+        Some(aws_sdk_s3::operation::get_object::GetObjectError::NoSuchKey(_))
+    )
+}
+
+#[async_trait::async_trait]
+impl Storage for S3Storage {
+    async fn insert(&self, collection: &str, doc: HashMap<String, serde_json::Value>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let id = doc.get("id").and_then(|v| v.as_str()).ok_or("document must have an 'id' field")?.to_string();
+
+        let mut final_doc = deep_copy_doc(&doc);
+
+        // Handle MEMORY blob
+        if let Some(serde_json::Value::String(entry_type)) = final_doc.get("entryType") {
+            if entry_type == "MEMORY" {
+                if let Some(serde_json::Value::Object(payload)) = final_doc.get_mut("payload") {
+                    if let Some(blob) = payload.remove("blob") {
+                        // Release the chunks the previous blobRef (if any) held
+                        // before installing the new one, so re-saving an
+                        // unchanged or edited MEMORY blob (via `update`'s
+                        // find-then-insert round trip) doesn't leak a
+                        // ref_count that `delete_blob` can never bring back
+                        // to zero.
+                        let previous_blob_ref = self.raw_blob_ref(collection, &id).await?;
+                        delete_blob(self, previous_blob_ref.as_ref()).await?;
+                        let blob_ref = save_blob(self, &blob).await?;
+                        payload.insert("blobRef".to_string(), blob_ref);
+                    }
+                }
+            }
+        }
+
+        // Encrypt sensitive collections
+        if is_encrypted_collection(collection) {
+            encrypt_document(&self.encryption_mgr, collection, &mut final_doc, self).await?;
+        }
+
+        let data = serde_json::to_vec(&final_doc)?;
+        self.put_object(&self.doc_key(collection, &id), data).await
+    }
+
+    async fn update(&self, collection: &str, id: &str, update: HashMap<String, serde_json::Value>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        if let Some(mut doc) = self.find(collection, id).await? {
+            for (k, v) in update {
+                doc.insert(k, v);
+            }
+            self.insert(collection, doc).await
+        } else {
+            Err("document not found".into())
+        }
+    }
+
+    async fn delete(&self, collection: &str, id: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        // Read the raw (still-encrypted, blobRef-as-manifest) document so we
+        // can reclaim its blob chunks, without routing through `find`'s
+        // decrypt/blob-load, which would already have swapped blobRef for
+        // the reassembled blob by the time we saw it.
+        let blob_ref = match self.get_object(&self.doc_key(collection, id)).await? {
+            Some(data) => {
+                let doc: HashMap<String, serde_json::Value> = serde_json::from_slice(&data)?;
+                doc.get("payload").and_then(|p| p.get("blobRef")).cloned()
+            }
+            None => None,
+        };
+
+        self.delete_object(&self.doc_key(collection, id)).await?;
+        delete_blob(self, blob_ref.as_ref()).await?;
+        Ok(())
+    }
+
+    async fn find(&self, collection: &str, id: &str) -> Result<Option<HashMap<String, serde_json::Value>>, Box<dyn std::error::Error + Send + Sync>> {
+        let data = match self.get_object(&self.doc_key(collection, id)).await? {
+            Some(data) => data,
+            None => return Ok(None),
+        };
+
+        let mut doc: HashMap<String, serde_json::Value> = serde_json::from_slice(&data)?;
+
+        // Decrypt if document is encrypted
+        if let Some(serde_json::Value::Bool(true)) = doc.get("encrypted") {
+            decrypt_document(&self.encryption_mgr, &mut doc).await?;
+        }
+
+        // Load blob for MEMORY
+        if let Some(serde_json::Value::String(entry_type)) = doc.get("entryType") {
+            if entry_type == "MEMORY" {
+                if let Some(blob_ref) = doc.get("payload").and_then(|p| p.get("blobRef")).cloned() {
+                    let blob = load_blob(self, &blob_ref).await?;
+                    if let Some(serde_json::Value::Object(payload)) = doc.get_mut("payload") {
+                        payload.insert("blob".to_string(), blob);
+                        payload.remove("blobRef");
+                    }
+                }
+            }
+        }
+
+        Ok(Some(doc))
+    }
+
+    async fn find_all(&self, collection: &str) -> Result<Vec<HashMap<String, serde_json::Value>>, Box<dyn std::error::Error + Send + Sync>> {
+        let mut docs = Vec::new();
+        for id in self.list_doc_ids(collection).await? {
+            if let Some(doc) = self.find(collection, &id).await? {
+                docs.push(doc);
+            }
+        }
+        Ok(docs)
+    }
+}
@@ -0,0 +1,53 @@
+use chrono::{DateTime, Duration, Utc};
+use parking_lot::RwLock;
+
+/// TimeSource abstracts wall-clock access so time-dependent behavior — key
+/// expiry, rotation, and CRDT last-writer-wins timestamps — can be driven
+/// deterministically in tests instead of depending on `Utc::now()` directly.
+/// Mirrors VPNCloud's `MockTimeSource` pattern: production code wires up
+/// `SystemTimeSource`, tests wire up `MockTimeSource` and advance it by hand.
+pub trait TimeSource: Send + Sync {
+    /// Now returns the current time according to this source.
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// SystemTimeSource is the real, `Utc::now()`-backed `TimeSource` used in
+/// production.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemTimeSource;
+
+impl TimeSource for SystemTimeSource {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// MockTimeSource is a `TimeSource` whose clock only moves when told to via
+/// `set`/`advance`, turning expiry, rotation, and LWW tie-break behavior
+/// into something a unit test can reproduce exactly.
+pub struct MockTimeSource {
+    now: RwLock<DateTime<Utc>>,
+}
+
+impl MockTimeSource {
+    /// New creates a mock clock starting at `start`.
+    pub fn new(start: DateTime<Utc>) -> Self {
+        MockTimeSource { now: RwLock::new(start) }
+    }
+
+    /// Set moves the mock clock to an absolute point in time.
+    pub fn set(&self, now: DateTime<Utc>) {
+        *self.now.write() = now;
+    }
+
+    /// Advance moves the mock clock forward by `delta`.
+    pub fn advance(&self, delta: Duration) {
+        *self.now.write() += delta;
+    }
+}
+
+impl TimeSource for MockTimeSource {
+    fn now(&self) -> DateTime<Utc> {
+        *self.now.read()
+    }
+}
@@ -2,6 +2,7 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use chrono::{DateTime, Utc, Duration};
 use crate::clock::VectorClock;
+use crate::codec::CodecKind;
 
 /// EntryType specifies the kind of data stored.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -77,9 +78,24 @@ pub struct CRDTOperation {
     pub document_id: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub data: Option<DistributedDocument>,
+    /// PayloadRef, when set, is the BLAKE3 hex digest `data`'s `payload`
+    /// was stored under in `crate::blockstore` because it exceeded
+    /// `NetworkConfig::inline_threshold` — `data.payload` itself is `None`
+    /// in that case, and a recipient without that block locally must fetch
+    /// it with `MessageType::BlockRequest` before merging this operation's
+    /// document.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payload_ref: Option<String>,
     pub vector: VectorClock,
     pub timestamp: i64,
     pub peer_id: String,
+    /// Detached ed25519 signature over `crypto::signable::Signable::signable_data`,
+    /// proving this operation really was produced by `peer_id` and hasn't
+    /// been tampered with in transit or at rest. `None` until `sign` is
+    /// called; `crypto::signable::Signable::verify` treats a missing
+    /// signature the same as an invalid one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signature: Option<Vec<u8>>,
 }
 
 /// NetworkConfig holds network-level configuration
@@ -100,6 +116,16 @@ pub struct NetworkConfig {
     pub auto_post_classifications: Vec<EntryType>,
     /// Entries are private by default unless staged or configured otherwise.
     pub private_by_default: bool,
+    /// Payload size, in bytes, above which a `CRDTOperation`'s document
+    /// payload is offloaded into the content-addressed block store instead
+    /// of traveling inline (see `crate::blockstore`). Zero uses the package
+    /// default (`blockstore::DEFAULT_INLINE_THRESHOLD`).
+    pub inline_threshold: usize,
+    /// Id of the post-quorum signing key this network's peers hold shares
+    /// of, set up via `DistributedDatabase::bootstrap_post_quorum_key`.
+    /// Empty disables threshold-signature promotion of staged entries: see
+    /// `crate::post_quorum` and `DistributedCollection::propose_post`.
+    pub post_quorum_key_id: String,
     pub encryption: EncryptionConfig,
     pub replication: ReplicationConfig,
     pub discovery: DiscoveryConfig,
@@ -121,6 +147,24 @@ pub struct ReplicationConfig {
 pub struct DiscoveryConfig {
     pub mdns: bool,
     pub bootstrap: bool,
+    /// BasaltEnabled turns on Basalt-style random peer sampling in addition
+    /// to the static `bootstrap_peers` list, giving the network a
+    /// self-stabilizing, Byzantine-resistant membership view instead of a
+    /// fixed set of dial targets.
+    pub basalt_enabled: bool,
+    /// ViewSize is the number of slots `k` in the sampled view. Zero is
+    /// treated as the package default (see `discovery::DEFAULT_VIEW_SIZE`).
+    pub view_size: usize,
+    /// ExchangeIntervalSecs controls how often this node gossips its view
+    /// to a random member of it. Zero is treated as the package default.
+    pub exchange_interval_secs: u64,
+    /// ResetIntervalSecs controls how often `reset_count` slots are
+    /// re-salted to evict stale or adversarial entries. Zero is treated as
+    /// the package default.
+    pub reset_interval_secs: u64,
+    /// ResetCount is how many slots are re-salted on each reset tick. Zero
+    /// is treated as the package default.
+    pub reset_count: usize,
 }
 
 /// PeerInfo
@@ -132,6 +176,28 @@ pub struct PeerInfo {
     pub latency: Duration,
     pub last_seen: DateTime<Utc>,
     pub collections: Vec<String>,
+    /// SupportedCodecs lists the `codec::CodecKind`s this peer has
+    /// advertised support for, most-preferred first, learned from its
+    /// `CollectionAnnounce` messages. Defaults to `[Json]` (the universal
+    /// fallback) for a peer that hasn't announced anything yet.
+    pub supported_codecs: Vec<CodecKind>,
+}
+
+/// NodeInfo is what a peer proves about itself during pairing (see
+/// `crate::pairing` and `network::register_pairing_handlers`): the
+/// collections and protocols it currently serves and its software version,
+/// exchanged in a `PairRequest`/`PairResponse`. `peer_id` is carried
+/// alongside rather than inferred so a receiver can check it against the
+/// connection's authenticated `ProtocolMessage::sender_id` before trusting
+/// the rest of the claim; the whole struct rides inside a `ProtocolMessage`
+/// already authenticated by that message's own signature, so `NodeInfo`
+/// carries no signature of its own.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NodeInfo {
+    pub peer_id: String,
+    pub collections: Vec<String>,
+    pub protocols: Vec<String>,
+    pub version: String,
 }
 
 /// SyncState for a collection/network
@@ -141,13 +207,51 @@ pub struct SyncState {
     pub network_id: String,
     pub local_vector: VectorClock,
     pub last_sync: DateTime<Utc>,
-    pub pending_operations: Vec<CRDTOperation>,
     /// StagedEntries contains IDs of documents marked with `_stage == "post-pending"`.
     /// These will be converted to KNIRVGRAPH transactions and posted during the next sync.
     pub staged_entries: Vec<String>,
+    /// PendingProposals tracks the threshold-signature quorum in progress for
+    /// each staged document this node has proposed, keyed by document id. See
+    /// `crate::post_quorum` and `DistributedCollection::propose_post`.
+    pub pending_proposals: HashMap<String, PostQuorum>,
     pub sync_in_progress: bool,
 }
 
+/// PostShare is one peer's contribution toward promoting a staged document
+/// to a KNIRVGRAPH transaction: literally that peer's Shamir share of the
+/// network's post-quorum signing key (see `crate::crypto::threshold`),
+/// carried over the wire in a `PostSig` message. A thin, serializable
+/// mirror of `crate::crypto::threshold::Share`, which carries no serde
+/// derives of its own.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PostShare {
+    pub index: u8,
+    pub data: Vec<u8>,
+}
+
+/// PostQuorum tracks one staged document's progress toward a combined
+/// threshold signature, from the `PostProposal` its proposer broadcast
+/// through however many `PostSig` replies have arrived so far. `shares` is
+/// keyed by contributing peer id so a duplicate `PostSig` from the same
+/// peer overwrites rather than double-counts toward `threshold`. Abandoned
+/// (removed from `SyncState::pending_proposals`) if the document is deleted
+/// or its vector clock advances again before quorum is reached — see
+/// `DistributedCollection::gc_pending_proposal`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PostQuorum {
+    pub collection: String,
+    pub document_id: String,
+    /// Id of the post-quorum signing key this document's shares are shares
+    /// of (see `crate::database::DistributedDatabase::bootstrap_post_quorum_key`).
+    pub key_id: String,
+    /// The document's vector clock at proposal time; if it no longer
+    /// matches the document's current clock, the proposal is stale and is
+    /// garbage-collected rather than finalized.
+    pub vector: VectorClock,
+    pub threshold: u8,
+    pub shares: HashMap<String, PostShare>,
+}
+
 /// NetworkStats
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct NetworkStats {
@@ -170,6 +274,60 @@ pub enum MessageType {
     Heartbeat,
     CollectionAnnounce,
     CollectionRequest,
+    /// PeerExchange carries a Basalt view (a `Vec<PeerInfo>`) gossiped
+    /// between members for peer sampling. See `crate::discovery`.
+    PeerExchange,
+    /// GetAddr requests the recipient's known-address table.
+    GetAddr,
+    /// Addr carries a capped `Vec<discovery::NodeEntry>` answering a
+    /// `GetAddr`, freshest entries first.
+    Addr,
+    /// ShareDistribute pushes one threshold-secret-sharing share (`index`
+    /// plus its byte data) for a master key to the peer that is to hold it.
+    /// See `crypto::threshold`.
+    ShareDistribute,
+    /// ShareRequest asks a peer for the share it holds for `key_id`, as part
+    /// of reconstructing a key during a decryption session.
+    ShareRequest,
+    /// ShareResponse answers a `ShareRequest` with the holder's `index` and
+    /// share data (or is never sent, if the holder has no share for that
+    /// key), correlated back to the request via `ProtocolMessage.request_id`.
+    ShareResponse,
+    /// OpSyncRequest carries a node's aggregate per-peer `VectorClock` for
+    /// one collection's operation log, asking the recipient to reply with
+    /// every `CRDTOperation` the sender's clock shows it is missing. See
+    /// `crate::replication::ReplicationEngine`.
+    OpSyncRequest,
+    /// OpSyncResponse answers an `OpSyncRequest` with exactly the missing
+    /// `CRDTOperation`s, correlated back via `ProtocolMessage.request_id`.
+    OpSyncResponse,
+    /// BlockRequest asks a peer for the block holding a `CRDTOperation`'s
+    /// offloaded payload, by its `payload_ref` hash. See
+    /// `crate::blockstore`.
+    BlockRequest,
+    /// BlockReply answers a `BlockRequest` with the block's bytes (or is
+    /// never sent, if the recipient doesn't hold that hash either),
+    /// correlated back via `ProtocolMessage.request_id`.
+    BlockReply,
+    /// PostProposal is broadcast by a peer promoting a staged document to a
+    /// KNIRVGRAPH transaction, carrying the document's canonical bytes and
+    /// the id of the post-quorum signing key shares should be drawn from.
+    /// See `crate::post_quorum`.
+    PostProposal,
+    /// PostSig answers a `PostProposal` with the replying peer's share of
+    /// the post-quorum signing key, sent directly back to the proposer
+    /// (not correlated via `request_id` — a proposer collects these
+    /// fire-and-forget until `threshold` distinct peers have replied).
+    PostSig,
+    /// PairRequest carries a `NodeInfo` (covered by the message's own
+    /// signature), scoped to `network_id`,
+    /// from the dialing side of a freshly authenticated connection, asking
+    /// the recipient to pair for that network. See `crate::pairing`.
+    PairRequest,
+    /// PairResponse answers a `PairRequest` with the replying peer's own
+    /// `NodeInfo` for the same `network_id`, completing pairing on both
+    /// sides. See `crate::pairing`.
+    PairResponse,
 }
 
 impl std::fmt::Display for MessageType {
@@ -181,6 +339,20 @@ impl std::fmt::Display for MessageType {
             MessageType::Heartbeat => write!(f, "heartbeat"),
             MessageType::CollectionAnnounce => write!(f, "collection_announce"),
             MessageType::CollectionRequest => write!(f, "collection_request"),
+            MessageType::PeerExchange => write!(f, "peer_exchange"),
+            MessageType::GetAddr => write!(f, "get_addr"),
+            MessageType::Addr => write!(f, "addr"),
+            MessageType::ShareDistribute => write!(f, "share_distribute"),
+            MessageType::ShareRequest => write!(f, "share_request"),
+            MessageType::ShareResponse => write!(f, "share_response"),
+            MessageType::OpSyncRequest => write!(f, "op_sync_request"),
+            MessageType::OpSyncResponse => write!(f, "op_sync_response"),
+            MessageType::BlockRequest => write!(f, "block_request"),
+            MessageType::BlockReply => write!(f, "block_reply"),
+            MessageType::PostProposal => write!(f, "post_proposal"),
+            MessageType::PostSig => write!(f, "post_sig"),
+            MessageType::PairRequest => write!(f, "pair_request"),
+            MessageType::PairResponse => write!(f, "pair_response"),
         }
     }
 }
@@ -192,5 +364,28 @@ pub struct ProtocolMessage {
     pub network_id: String,
     pub sender_id: String,
     pub timestamp: i64,
-    pub payload: serde_json::Value,
+    /// Payload carries `content_codec`-encoded bytes rather than a bare
+    /// `serde_json::Value`, so a sender can use whatever wire format it and
+    /// the recipient have negotiated (see `codec::CodecKind::negotiate`)
+    /// without the struct itself needing to know which one. Build/read this
+    /// with `codec::json_payload`/`codec::payload_value` unless a
+    /// non-default codec has actually been negotiated for the recipient.
+    pub payload: Vec<u8>,
+    /// ContentCodec identifies which `codec::Codec` `payload` was encoded
+    /// with, so every message self-describes its own encoding and decoding
+    /// never requires out-of-band state.
+    pub content_codec: CodecKind,
+    /// RequestID correlates a response to the request that triggered it. Set
+    /// by `NetworkManager::request` on the outgoing message and echoed back
+    /// unchanged by the handler that replies to it; fire-and-forget messages
+    /// leave this unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<u64>,
+    /// Detached ed25519 signature over `crypto::signable::Signable::signable_data`,
+    /// set by `NetworkManager` on every outgoing message and checked on
+    /// every inbound one, so a message can't be forged or altered in
+    /// transit while still claiming `sender_id`. See
+    /// `crypto::signable::Signable`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signature: Option<Vec<u8>>,
 }
\ No newline at end of file